@@ -0,0 +1,26 @@
+//! Plays a game steered by the fuzzer input, with `cargo fuzz run random_walk`.
+//!
+//! The first 8 bytes seed the game RNG and the next picks the start point.
+//! Each byte after that picks one of the legal moves, and the walk ends when
+//! the input runs out.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use ts_engine::fuzz::{walk, STARTS};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 9 {
+        return;
+    }
+    let mut seed = [0; 8];
+    seed.copy_from_slice(&data[..8]);
+    let seed = u64::from_le_bytes(seed);
+    let start = STARTS[data[8] as usize % STARTS.len()];
+    let mut moves = data[9..].iter();
+    let res = walk(seed, start, data.len(), |legal| {
+        moves.next().map(|&b| legal[b as usize % legal.len()])
+    });
+    if let Err(failure) = res {
+        panic!("{:?}", failure);
+    }
+});
@@ -4,6 +4,8 @@ use crate::state::GameState;
 use crate::tensor::{self, OutputVec};
 
 use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 lazy_static! {
     static ref OFFSETS: Vec<usize> = {
@@ -24,6 +26,7 @@ pub const CUBAN_OFFSET: usize = Action::CubanMissile as usize;
 pub const PASS: usize = Action::Pass as usize;
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Decision {
     pub agent: Side,
     pub action: Action,
@@ -214,6 +217,20 @@ impl Decision {
                 self.allowed = Allowed::new_owned(allowed);
                 Some(self)
             }
+            Action::ChooseCard if state.current_event().unwrap() == Card::Our_Man_In_Tehran => {
+                // Each card can only be discarded once
+                if let Some(last) = history.last().map(|c| c.choice).flatten() {
+                    self.allowed = self
+                        .allowed
+                        .force_slice(state)
+                        .iter()
+                        .copied()
+                        .filter(|x| *x != last)
+                        .collect::<Vec<_>>()
+                        .into();
+                }
+                Some(self)
+            }
             _ => {
                 state.apply_restriction(history, &mut self);
                 Some(self)
@@ -224,25 +241,25 @@ impl Decision {
         assert!(self.action == Action::Influence); // For now?
         if self.quantity == 0 {
             None
-        } else if self.quantity == 1 {
-            let opp = self.agent.opposite();
+        } else {
+            // What is left may not cover breaking control or losing a bonus
+            let legal = state.legal_influence(self.agent, self.quantity);
             let allowed: Vec<_> = self
                 .allowed
                 .force_slice(state)
                 .iter()
                 .copied()
-                .filter(|x| !state.is_controlled(opp, *x))
+                .filter(|x| legal.contains(x))
                 .collect();
             let allowed = Allowed::new_owned(allowed);
             self.allowed = allowed;
             Some(self)
-        } else {
-            Some(self)
         }
     }
 }
 
 #[derive(Clone, Copy, FromPrimitive, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Action {
     BeginAr = 0,
     EndAr,
@@ -323,12 +340,18 @@ pub enum EventTime {
     Never = 2,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Restriction {
     Limit(usize),
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "AllowedRepr", try_from = "AllowedRepr")
+)]
 /// Abstraction across data which is known at compile time and data that must be
 /// computed on the fly.
 pub struct Allowed {
@@ -349,9 +372,9 @@ impl Allowed {
             allowed: AllowedType::Empty,
         }
     }
-    pub fn new_lazy(f: fn(&GameState) -> Vec<usize>) -> Allowed {
+    pub fn new_lazy(lazy: LazyAllowed) -> Allowed {
         Allowed {
-            allowed: AllowedType::Lazy(f),
+            allowed: AllowedType::Lazy(lazy),
         }
     }
     pub fn new_unknown() -> Allowed {
@@ -381,25 +404,45 @@ impl Allowed {
     fn force_iter<'a>(&'a self, state: &GameState) -> Box<dyn Iterator<Item = usize> + 'a> {
         //! This boxing incurs a small amount of overhead to avoid making the
         //! calling code explicitly handle both cases
-        if let AllowedType::Lazy(f) = self.allowed {
-            Box::new(f(state).into_iter())
+        if let AllowedType::Lazy(lazy) = self.allowed {
+            Box::new(lazy.compute(state).into_iter())
         } else {
             Box::new(self.try_slice().unwrap().iter().copied())
         }
     }
     fn resolve(&self, state: &GameState) -> Option<Allowed> {
-        if let AllowedType::Lazy(f) = self.allowed {
-            Some(Allowed::new_owned(f(state)))
+        if let AllowedType::Lazy(lazy) = self.allowed {
+            Some(Allowed::new_owned(lazy.compute(state)))
         } else {
             None
         }
     }
 }
 
+/// Allowed data that depends on the state when it is read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LazyAllowed {
+    MuslimRev,
+    Norad,
+    BonusInfluence,
+}
+
+impl LazyAllowed {
+    fn compute(self, state: &GameState) -> Vec<usize> {
+        match self {
+            LazyAllowed::MuslimRev => crate::card::legal::muslim_rev(state),
+            LazyAllowed::Norad => crate::card::legal::norad(state),
+            LazyAllowed::BonusInfluence => crate::game::legal_bonus_influence(state),
+        }
+    }
+}
+
 #[derive(Clone)]
 enum AllowedType {
     Slice(&'static [usize]),
-    Lazy(fn(&GameState) -> Vec<usize>),
+    Lazy(LazyAllowed),
     Owned(Vec<usize>),
     Empty,
     Unknown, // Unable to be read, used for meta types
@@ -417,6 +460,44 @@ impl std::fmt::Debug for AllowedType {
     }
 }
 
+/// Serialized form of `Allowed`. Static slices are stored by value and come
+/// back as owned vectors, and lazy data is stored by its kind.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+enum AllowedRepr {
+    Values(Vec<usize>),
+    Lazy(LazyAllowed),
+    Empty,
+    Unknown,
+}
+
+#[cfg(feature = "serde")]
+impl From<Allowed> for AllowedRepr {
+    fn from(allowed: Allowed) -> Self {
+        match allowed.allowed {
+            AllowedType::Slice(s) => AllowedRepr::Values(s.to_vec()),
+            AllowedType::Owned(v) => AllowedRepr::Values(v),
+            AllowedType::Lazy(lazy) => AllowedRepr::Lazy(lazy),
+            AllowedType::Empty => AllowedRepr::Empty,
+            AllowedType::Unknown => AllowedRepr::Unknown,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<AllowedRepr> for Allowed {
+    type Error = String;
+    fn try_from(repr: AllowedRepr) -> Result<Self, Self::Error> {
+        let allowed = match repr {
+            AllowedRepr::Values(v) => Allowed::new_owned(v),
+            AllowedRepr::Lazy(lazy) => Allowed::new_lazy(lazy),
+            AllowedRepr::Empty => Allowed::new_empty(),
+            AllowedRepr::Unknown => Allowed::new_unknown(),
+        };
+        Ok(allowed)
+    }
+}
+
 impl From<Vec<usize>> for Allowed {
     fn from(vec: Vec<usize>) -> Self {
         Allowed::new_owned(vec)
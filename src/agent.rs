@@ -1,12 +1,20 @@
 use crate::action::{self, Decision};
 use crate::country::Side;
 use crate::game::Game;
-use crate::state::{DebugRand, GameState};
+use crate::state::{DebugRand, GameState, PlayerView};
 use crate::tensor::{DecodedChoice, OutputIndex, OutputVec, TensorOutput};
 
 use rand::prelude::*;
+use rand::rngs::SmallRng;
 use std::sync::Mutex;
 
+pub mod mcts;
+pub use mcts::*;
+#[cfg(feature = "serde")]
+pub mod remote;
+#[cfg(feature = "serde")]
+pub use remote::RemoteAgent;
+
 pub struct Actors<A: Agent, B: Agent> {
     pub ussr_agent: A,
     pub us_agent: B,
@@ -45,9 +53,9 @@ where
 }
 
 pub trait Agent {
-    /// Given a game state and encoding of all legal actions, decide which
-    /// action to take and return the action the desired index
-    fn decide(&self, state: &GameState, legal: OutputVec) -> DecodedChoice;
+    /// Given the agent's view of the game and encoding of all legal actions,
+    /// decide which action to take and return the action the desired index
+    fn decide(&self, view: &PlayerView, legal: OutputVec) -> DecodedChoice;
     /// Returns which side the agent is playing
     fn side(&self) -> Side;
     /// Returns just the evaluation of the given position
@@ -89,7 +97,7 @@ impl Agent for ScriptedAgent {
     fn get_eval(&self, _state: &GameState) -> f32 {
         todo!()
     }
-    fn decide(&self, _state: &GameState, legal: OutputVec) -> DecodedChoice {
+    fn decide(&self, _view: &PlayerView, legal: OutputVec) -> DecodedChoice {
         // Check for Cuban Missile Crisis action and Pass if we do not resolve it
         if 2 <= legal.len() && legal.len() <= 4 {
             let first_non = legal.iter().find(|x| x.inner() != action::PASS);
@@ -106,7 +114,7 @@ impl Agent for ScriptedAgent {
         }
         let next = self.next().unwrap();
         if !legal.contains(&next) {
-            dbg!(_state.ar);
+            dbg!(_view.state().ar);
             dbg!(legal);
             dbg!(next);
             panic!("Legal does not contain next!");
@@ -118,20 +126,30 @@ impl Agent for ScriptedAgent {
     }
 }
 
-pub struct RandAgent {}
+pub struct RandAgent {
+    rng: Mutex<SmallRng>,
+}
 impl RandAgent {
     pub fn new() -> Self {
-        RandAgent {}
+        RandAgent {
+            rng: Mutex::new(SmallRng::from_entropy()),
+        }
+    }
+    /// Creates an agent whose choices and evaluations are reproducible from
+    /// the seed.
+    pub fn new_seeded(seed: u64) -> Self {
+        RandAgent {
+            rng: Mutex::new(SmallRng::seed_from_u64(seed)),
+        }
     }
 }
 
 impl Agent for RandAgent {
     fn get_eval(&self, _state: &GameState) -> f32 {
-        thread_rng().gen()
+        self.rng.lock().unwrap().gen()
     }
-    fn decide(&self, _state: &GameState, legal: OutputVec) -> DecodedChoice {
-        let mut rng = thread_rng();
-        let x = legal.choose(&mut rng);
+    fn decide(&self, _view: &PlayerView, legal: OutputVec) -> DecodedChoice {
+        let x = legal.choose(&mut *self.rng.lock().unwrap());
         if let Some(choice) = x {
             choice.decode()
         } else {
@@ -0,0 +1,355 @@
+use super::Agent;
+use crate::country::Side;
+use crate::game::Game;
+use crate::state::{GameState, InternalRand, PlayerView, Win};
+use crate::tensor::{DecodedChoice, OutputIndex, OutputVec};
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Supplies prior probabilities over legal actions for PUCT selection.
+pub trait Policy {
+    /// Returns one prior per entry of `legal`, in the same order.
+    fn priors(&self, state: &GameState, legal: &[OutputIndex]) -> Vec<f32>;
+}
+
+/// A policy that assigns equal weight to every legal action.
+pub struct UniformPolicy;
+
+impl Policy for UniformPolicy {
+    fn priors(&self, _state: &GameState, legal: &[OutputIndex]) -> Vec<f32> {
+        vec![1.0 / legal.len() as f32; legal.len()]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Selection {
+    /// UCB1 applied to trees, with the given exploration constant.
+    Uct(f32),
+    /// The AlphaZero variant, weighting exploration by the policy prior.
+    Puct(f32),
+}
+
+#[derive(Clone, Debug)]
+pub struct MctsConfig {
+    pub selection: Selection,
+    /// Maximum number of simulations per decision.
+    pub simulations: usize,
+    /// Optional wall clock limit per decision, checked between simulations.
+    pub time_limit: Option<Duration>,
+    /// Maximum number of actions the rollout agent plays from a new leaf
+    /// before the game is scored by the VP track.
+    pub rollout_depth: usize,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            selection: Selection::Uct(1.4),
+            simulations: 200,
+            time_limit: None,
+            rollout_depth: 50,
+        }
+    }
+}
+
+struct Node {
+    /// The side that chose the action leading to this node.
+    side: Side,
+    prior: f32,
+    visits: u32,
+    /// Number of simulations in which this node's action was legal.
+    avail: u32,
+    /// Sum of rewards, from the perspective of `side`.
+    value: f32,
+    children: Vec<(OutputIndex, usize)>,
+}
+
+impl Node {
+    fn new(side: Side, prior: f32) -> Self {
+        Node {
+            side,
+            prior,
+            visits: 0,
+            avail: 0,
+            value: 0.0,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// An information set Monte Carlo Tree Search agent. Each simulation plays
+/// out a fresh determinization of the agent's view, so hidden cards are never
+/// peeked at. New leaves are scored by letting the rollout agent play both
+/// sides, either to the end of the game or for `rollout_depth` actions, after
+/// which the VP track decides.
+pub struct MctsAgent<A: Agent, P: Policy> {
+    side: Side,
+    rollout: A,
+    policy: P,
+    config: MctsConfig,
+    rng: Mutex<SmallRng>,
+}
+
+impl<A: Agent, P: Policy> MctsAgent<A, P> {
+    pub fn new(side: Side, rollout: A, policy: P, config: MctsConfig, seed: u64) -> Self {
+        MctsAgent {
+            side,
+            rollout,
+            policy,
+            config,
+            rng: Mutex::new(SmallRng::seed_from_u64(seed)),
+        }
+    }
+    pub fn config(&self) -> &MctsConfig {
+        &self.config
+    }
+    /// Runs a single simulation from the root and backs up its value.
+    fn simulate(&self, tree: &mut Vec<Node>, mut game: Game<InternalRand>, root: &[OutputIndex]) {
+        let mut path = vec![0];
+        let mut legal = root.to_vec();
+        let value = loop {
+            let node = *path.last().unwrap();
+            if node != 0 && tree[node].visits == 0 {
+                break self.rollout(&mut game);
+            }
+            if legal.is_empty() {
+                legal.push(OutputIndex::pass());
+            }
+            let agent = game.state.acting_side();
+            let child = self.select(tree, node, agent, &game.state, &legal);
+            path.push(child);
+            let (index, _) = tree[node]
+                .children
+                .iter()
+                .find(|(_, c)| *c == child)
+                .unwrap();
+            match game.consume_action(index.decode()) {
+                Ok(_) => legal = game.legal(),
+                Err(win) => break self.terminal_value(win),
+            }
+        };
+        tree[0].visits += 1;
+        for &i in path[1..].iter() {
+            let node = &mut tree[i];
+            node.visits += 1;
+            node.value += if node.side == self.side {
+                value
+            } else {
+                1.0 - value
+            };
+        }
+    }
+    /// Adds any unseen legal actions as children and returns the index of the
+    /// best child among those legal in this determinization.
+    fn select(
+        &self,
+        tree: &mut Vec<Node>,
+        node: usize,
+        agent: Side,
+        state: &GameState,
+        legal: &[OutputIndex],
+    ) -> usize {
+        let mut priors = None;
+        let mut available = Vec::with_capacity(legal.len());
+        for (i, x) in legal.iter().enumerate() {
+            let found = tree[node].children.iter().find(|(y, _)| y == x);
+            let child = match found {
+                Some(&(_, c)) => c,
+                None => {
+                    let p = priors.get_or_insert_with(|| self.policy.priors(state, legal));
+                    tree.push(Node::new(agent, p[i]));
+                    let c = tree.len() - 1;
+                    tree[node].children.push((*x, c));
+                    c
+                }
+            };
+            tree[child].avail += 1;
+            available.push(child);
+        }
+        let mut best = available[0];
+        let mut best_score = f32::NEG_INFINITY;
+        for &c in available.iter() {
+            let score = self.score(&tree[c]);
+            if score > best_score {
+                best = c;
+                best_score = score;
+            }
+        }
+        best
+    }
+    fn score(&self, node: &Node) -> f32 {
+        let n = node.visits as f32;
+        let avail = node.avail as f32;
+        let q = if node.visits == 0 {
+            0.0
+        } else {
+            node.value / n
+        };
+        match self.config.selection {
+            Selection::Uct(c) => {
+                if node.visits == 0 {
+                    f32::INFINITY
+                } else {
+                    q + c * (avail.ln() / n).sqrt()
+                }
+            }
+            Selection::Puct(c) => q + c * node.prior * avail.sqrt() / (1.0 + n),
+        }
+    }
+    /// Plays the rollout agent from the leaf and returns the value reached.
+    fn rollout(&self, game: &mut Game<InternalRand>) -> f32 {
+        for _ in 0..self.config.rollout_depth {
+            let mut legal = game.legal();
+            if legal.is_empty() {
+                legal.push(OutputIndex::pass());
+            }
+            let choice = if legal.len() == 1 {
+                legal[0].decode()
+            } else {
+                let view = game.state.observe(game.state.acting_side());
+                self.rollout.decide(&view, legal)
+            };
+            if let Err(win) = game.consume_action(choice) {
+                return self.terminal_value(win);
+            }
+        }
+        self.vp_value(&game.state)
+    }
+    /// Scores an unfinished game by the VP track, from 0 when the other side
+    /// is about to win to 1 when this one is.
+    fn vp_value(&self, state: &GameState) -> f32 {
+        let us = ((f32::from(state.vp) + 20.0) / 40.0).clamp(0.0, 1.0);
+        if self.side == Side::US {
+            us
+        } else {
+            1.0 - us
+        }
+    }
+    fn terminal_value(&self, win: Win) -> f32 {
+        match win.winner() {
+            s if s == self.side => 1.0,
+            Side::Neutral => 0.5,
+            _ => 0.0,
+        }
+    }
+}
+
+impl<A: Agent, P: Policy> Agent for MctsAgent<A, P> {
+    fn decide(&self, view: &PlayerView, legal: OutputVec) -> DecodedChoice {
+        if legal.len() == 1 {
+            return legal[0].decode();
+        }
+        let mut rng = self.rng.lock().unwrap();
+        let mut tree = vec![Node::new(self.side, 1.0)];
+        let start = Instant::now();
+        for i in 0..self.config.simulations {
+            // Always run at least one simulation so there is a move to return
+            if let (true, Some(limit)) = (i > 0, self.config.time_limit) {
+                if start.elapsed() >= limit {
+                    break;
+                }
+            }
+            let state = view.determinize(&mut *rng);
+            let game = Game::resume(state, InternalRand::new_seeded(rng.gen()));
+            self.simulate(&mut tree, game, &legal);
+        }
+        let (best, _) = tree[0]
+            .children
+            .iter()
+            .max_by_key(|(_, c)| tree[*c].visits)
+            .expect("At least one simulation");
+        best.decode()
+    }
+    fn side(&self) -> Side {
+        self.side
+    }
+    fn get_eval(&self, state: &GameState) -> f32 {
+        self.vp_value(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::RandAgent;
+    use crate::game::{RuleSet, Start};
+
+    #[test]
+    fn test_headline_search() {
+        let state = GameState::four_four_two();
+        let mut game = Game::new(state, InternalRand::new_seeded(3), RuleSet::default());
+        game.setup(Start::HL(1));
+        let side = game.state.acting_side();
+        for &selection in [Selection::Uct(1.4), Selection::Puct(1.4)].iter() {
+            let config = MctsConfig {
+                selection,
+                simulations: 50,
+                ..MctsConfig::default()
+            };
+            let agent = || {
+                let rollout = RandAgent::new_seeded(5);
+                MctsAgent::new(side, rollout, UniformPolicy, config.clone(), 11)
+            };
+            let legal = game.legal();
+            let view = game.state.observe(side);
+            let choice = agent().decide(&view, legal.clone());
+            assert!(legal.iter().any(|x| x.decode() == choice));
+            // Seeded agents search the same way every time
+            assert_eq!(agent().decide(&view, legal.clone()), choice);
+        }
+    }
+
+    #[test]
+    fn test_winning_move() {
+        use crate::action::Action;
+        use crate::card::{Card, Pile};
+        use crate::country::{CName, MIDDLE_EAST};
+        let state = GameState::four_four_two();
+        let mut game = Game::new(state, InternalRand::new_seeded(3), RuleSet::default());
+        game.setup(Start::FirstAR(1));
+        // The USSR is three VP short of winning and holds the Middle East
+        // Scoring card, which gives it four
+        let state = &mut game.state;
+        for card in state.deck.ussr_hand().clone() {
+            state
+                .move_card(card, Pile::Hand(Side::USSR), Pile::Draw)
+                .unwrap();
+        }
+        let cards = [
+            Card::Middle_East_Scoring,
+            Card::Comecon,
+            Card::Decolonization,
+            Card::De_Gaulle_Leads_France,
+        ];
+        for &card in cards.iter() {
+            let from = if state.deck.us_hand().contains(&card) {
+                Pile::Hand(Side::US)
+            } else {
+                Pile::Draw
+            };
+            state.move_card(card, from, Pile::Hand(Side::USSR)).unwrap();
+        }
+        for &c in MIDDLE_EAST.iter() {
+            state.set_influence(Side::US, c, 0);
+        }
+        state.set_influence(Side::USSR, CName::Iraq, 3);
+        state.set_vp(-17);
+        // Rollouts end before the USSR could score on a later turn, which
+        // leaves the other moves a few VP short, so explore less
+        let config = MctsConfig {
+            selection: Selection::Uct(0.2),
+            simulations: 100,
+            rollout_depth: 4,
+            ..MctsConfig::default()
+        };
+        let rollout = RandAgent::new_seeded(5);
+        let agent = MctsAgent::new(Side::USSR, rollout, UniformPolicy, config, 11);
+        let view = game.state.observe(Side::USSR);
+        let choice = agent.decide(&view, game.legal());
+        let scoring = Card::Middle_East_Scoring as usize;
+        assert_eq!(choice, DecodedChoice::new(Action::Event, Some(scoring)));
+    }
+}
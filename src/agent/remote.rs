@@ -0,0 +1,298 @@
+use super::Agent;
+use crate::country::Side;
+use crate::game::{EndReason, GameOutcome};
+use crate::state::{GameState, PlayerView};
+use crate::tensor::{
+    ActionSpace, ActionSpaceId, DecodedChoice, ObservationEncoder, OutputIndex, OutputVec,
+};
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bumped whenever a message of the protocol changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A message from the engine to a remote agent, sent as one line of JSON
+/// tagged by its `type`, e.g. `{"type":"decide","id":3,...}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Sent once on connecting, with the layouts the agent must understand.
+    Hello {
+        version: u32,
+        side: Side,
+        action_space: ActionSpaceId,
+        observation_version: u32,
+        observation_size: usize,
+        timeout_ms: u64,
+    },
+    /// Starts a game, which ends with `GameOver` unless the engine fails.
+    NewGame { seed: u64 },
+    /// Asks for one of the legal action indices. The reply must repeat the
+    /// id and arrive within the timeout.
+    Decide {
+        id: u64,
+        turn: i8,
+        ar: i8,
+        observation: Vec<f32>,
+        legal: Vec<usize>,
+    },
+    /// The reply to a request was missing or invalid, so the engine played
+    /// a random legal action for the agent instead.
+    Rejected {
+        id: u64,
+        reason: String,
+        played: usize,
+    },
+    GameOver {
+        winner: Option<Side>,
+        reason: EndReason,
+        vp: i8,
+    },
+}
+
+/// The reply of a remote agent to `ServerMessage::Decide`, e.g.
+/// `{"id":3,"action":512}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentReply {
+    pub id: u64,
+    pub action: usize,
+}
+
+/// An agent in another process, speaking line-delimited JSON over a pipe or
+/// a socket.
+///
+/// The engine stays in charge of the rules: a reply that is late, malformed
+/// or illegal counts as a fault and a random legal action is played in its
+/// place, so a misbehaving agent cannot stall or corrupt the game.
+pub struct RemoteAgent {
+    side: Side,
+    timeout: Duration,
+    encoder: ObservationEncoder,
+    writer: Mutex<Box<dyn Write + Send>>,
+    replies: Mutex<Receiver<io::Result<String>>>,
+    next_id: AtomicU64,
+    faults: AtomicUsize,
+    rng: Mutex<SmallRng>,
+}
+
+impl RemoteAgent {
+    /// Connects to an agent reading requests from `writer` and replying on
+    /// `reader`, and sends it the greeting.
+    pub fn new<R, W>(side: Side, reader: R, writer: W, timeout: Duration) -> io::Result<Self>
+    where
+        R: BufRead + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        // Replies are read on their own thread so that waiting can time out
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let encoder = ObservationEncoder::new();
+        let hello = ServerMessage::Hello {
+            version: PROTOCOL_VERSION,
+            side,
+            action_space: ActionSpace::new().id(),
+            observation_version: encoder.version(),
+            observation_size: encoder.size(),
+            timeout_ms: timeout.as_millis() as u64,
+        };
+        let agent = RemoteAgent {
+            side,
+            timeout,
+            encoder,
+            writer: Mutex::new(Box::new(writer)),
+            replies: Mutex::new(rx),
+            next_id: AtomicU64::new(0),
+            faults: AtomicUsize::new(0),
+            rng: Mutex::new(SmallRng::seed_from_u64(0)),
+        };
+        agent.send(&hello)?;
+        Ok(agent)
+    }
+    /// Talks to an agent over a TCP connection.
+    pub fn tcp(side: Side, stream: TcpStream, timeout: Duration) -> io::Result<Self> {
+        let reader = BufReader::new(stream.try_clone()?);
+        RemoteAgent::new(side, reader, stream, timeout)
+    }
+    /// Talks to an agent over this process's stdin and stdout.
+    pub fn stdio(side: Side, timeout: Duration) -> io::Result<Self> {
+        RemoteAgent::new(side, BufReader::new(io::stdin()), io::stdout(), timeout)
+    }
+    /// Returns how many replies were rejected so far.
+    pub fn faults(&self) -> usize {
+        self.faults.load(Ordering::SeqCst)
+    }
+    /// Tells the agent a new game is starting.
+    pub fn new_game(&self, seed: u64) -> io::Result<()> {
+        self.send(&ServerMessage::NewGame { seed })
+    }
+    /// Tells the agent how the game ended.
+    pub fn game_over(&self, outcome: &GameOutcome) -> io::Result<()> {
+        self.send(&ServerMessage::GameOver {
+            winner: outcome.winner,
+            reason: outcome.reason,
+            vp: outcome.vp,
+        })
+    }
+    fn send(&self, msg: &ServerMessage) -> io::Result<()> {
+        let line = serde_json::to_string(msg)?;
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{}", line)?;
+        writer.flush()
+    }
+    /// Waits for the reply to request `id`, skipping late replies to earlier
+    /// requests.
+    fn receive(&self, id: u64, legal: &[OutputIndex]) -> Result<OutputIndex, String> {
+        let deadline = Instant::now() + self.timeout;
+        let replies = self.replies.lock().unwrap();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let line = match replies.recv_timeout(left) {
+                Ok(Ok(line)) => line,
+                Ok(Err(e)) => return Err(e.to_string()),
+                Err(RecvTimeoutError::Timeout) => return Err("timed out".to_string()),
+                Err(RecvTimeoutError::Disconnected) => return Err("disconnected".to_string()),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply: AgentReply =
+                serde_json::from_str(&line).map_err(|e| format!("bad reply: {}", e))?;
+            if reply.id != id {
+                continue;
+            }
+            let x = OutputIndex::new(reply.action);
+            return if legal.contains(&x) {
+                Ok(x)
+            } else {
+                Err(format!("illegal action {}", reply.action))
+            };
+        }
+    }
+}
+
+impl Agent for RemoteAgent {
+    fn decide(&self, view: &PlayerView, legal: OutputVec) -> DecodedChoice {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let state = view.state();
+        let msg = ServerMessage::Decide {
+            id,
+            turn: state.turn,
+            ar: state.ar,
+            observation: self.encoder.encode(state, view.side()),
+            legal: legal.iter().map(|x| x.inner()).collect(),
+        };
+        let res = self
+            .send(&msg)
+            .map_err(|e| e.to_string())
+            .and_then(|_| self.receive(id, &legal));
+        match res {
+            Ok(x) => x.decode(),
+            Err(reason) => {
+                self.faults.fetch_add(1, Ordering::SeqCst);
+                let x = *legal
+                    .choose(&mut *self.rng.lock().unwrap())
+                    .expect("Nothing to decide!");
+                // The agent may be gone, which the next request notices too
+                let _ = self.send(&ServerMessage::Rejected {
+                    id,
+                    reason,
+                    played: x.inner(),
+                });
+                x.decode()
+            }
+        }
+    }
+    fn side(&self) -> Side {
+        self.side
+    }
+    fn get_eval(&self, _state: &GameState) -> f32 {
+        // Remote agents are only asked for decisions
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, Start};
+    use std::net::TcpListener;
+
+    fn decide(msg: ServerMessage) -> (u64, Vec<usize>) {
+        match msg {
+            ServerMessage::Decide { id, legal, .. } => (id, legal),
+            msg => panic!("Expected decide, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn test_remote_agent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Replies with an illegal action, then too late, then correctly
+        let bot = thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut read = || {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                serde_json::from_str::<ServerMessage>(&line).unwrap()
+            };
+            let mut reply = |id, action| {
+                let reply = AgentReply { id, action };
+                writeln!(writer, "{}", serde_json::to_string(&reply).unwrap()).unwrap();
+            };
+            match read() {
+                ServerMessage::Hello { side, .. } => assert_eq!(side, Side::US),
+                msg => panic!("Expected hello, got {:?}", msg),
+            }
+            let mut rejected = Vec::new();
+            let (id, _) = decide(read());
+            reply(id, usize::MAX);
+            rejected.push(read());
+            let (late, legal) = decide(read());
+            rejected.push(read());
+            let (id, _) = decide(read());
+            reply(late, legal[0]);
+            reply(id, legal[legal.len() - 1]);
+            rejected
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let agent = RemoteAgent::tcp(Side::US, stream, Duration::from_millis(100)).unwrap();
+        let mut game = Game::new_seeded(3, Start::FirstAR(1));
+        let view = game.state.observe(Side::US);
+        let legal = game.legal();
+        for _ in 0..2 {
+            let choice = agent.decide(&view, legal.clone());
+            let x = ActionSpace::new().encode(&choice).unwrap();
+            assert!(legal.contains(&x));
+        }
+        let choice = agent.decide(&view, legal.clone());
+        let x = ActionSpace::new().encode(&choice).unwrap();
+        assert_eq!(x, *legal.last().unwrap());
+        assert_eq!(agent.faults(), 2);
+        let rejected = bot.join().unwrap();
+        for (id, msg) in rejected.into_iter().enumerate() {
+            match msg {
+                ServerMessage::Rejected { id: r, .. } => assert_eq!(r, id as u64),
+                msg => panic!("Expected rejected, got {:?}", msg),
+            }
+        }
+    }
+}
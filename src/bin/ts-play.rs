@@ -0,0 +1,328 @@
+//! Plays a game from the terminal against an agent.
+//!
+//! ```text
+//! ts-play [--side us|ussr] [--opponent AGENT] [--seed N] [--load FILE]
+//! ```
+//!
+//! The opponent is `rand` or `mcts[:simulations]`. Whenever the human side
+//! has a decision the legal actions are listed, and one is chosen by its
+//! number or by name: an optional action followed by a card or country, such
+//! as `coup iran`, `ev duck` or just `duck`. Any part may be shortened to a
+//! prefix as long as only one legal action matches.
+//!
+//! The other commands are `board`, `undo`, `save FILE`, `load FILE`, `help`
+//! and `quit`. Undo takes back the last human action along with the agent's
+//! replies. Saving and loading need the `serde` feature.
+
+use ts_engine::agent::Agent;
+use ts_engine::country::Side;
+use ts_engine::game::{Game, Start};
+use ts_engine::harness::{AgentKind, Stream};
+use ts_engine::record::find_card;
+use ts_engine::state::{GameState, InternalRand};
+use ts_engine::tensor::{ActionSpace, ChoiceKind, DecodedChoice, OutputIndex};
+
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const HELP: &str = "Choose an action by number or name, e.g. `3`, `coup iran` or `ev duck`.
+Commands: board, undo, save FILE, load FILE, help, quit";
+
+struct Config {
+    side: Side,
+    opponent: AgentKind,
+    seed: u64,
+    load: Option<String>,
+}
+
+impl Config {
+    fn from_args() -> Result<Config, String> {
+        let mut config = Config {
+            side: Side::US,
+            opponent: AgentKind::Rand,
+            seed: 0,
+            load: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            let bad = || format!("Bad value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--side" => config.side = parse_side(&value).ok_or_else(bad)?,
+                "--opponent" => config.opponent = AgentKind::parse(&value).ok_or_else(bad)?,
+                "--seed" => config.seed = value.parse().map_err(|_| bad())?,
+                "--load" => config.load = Some(value),
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn parse_side(s: &str) -> Option<Side> {
+    match s.to_ascii_lowercase().as_str() {
+        "us" => Some(Side::US),
+        "ussr" => Some(Side::USSR),
+        _ => None,
+    }
+}
+
+struct Session {
+    game: Game<InternalRand>,
+    human: Side,
+    opponent: Box<dyn Agent>,
+    space: ActionSpace,
+    /// The number of actions taken from each human decision up to the next,
+    /// so that undo can take back the agent's replies too.
+    history: Vec<usize>,
+}
+
+impl Session {
+    fn new(config: &Config) -> Result<Session, String> {
+        let opponent_side = match config.side {
+            Side::US => Side::USSR,
+            _ => Side::US,
+        };
+        let mut session = Session {
+            game: Game::new_seeded(Stream::Game.seed(config.seed), Start::Beginning),
+            human: config.side,
+            opponent: config.opponent.build(opponent_side, config.seed),
+            space: ActionSpace::new(),
+            history: Vec::new(),
+        };
+        match config.load.as_ref() {
+            Some(path) => session.load(path, config.seed)?,
+            None => session.start(),
+        }
+        Ok(session)
+    }
+    /// Starts recording undo information and events for the current game.
+    fn start(&mut self) {
+        self.game.set_undo(true);
+        self.game.set_event_log(true);
+        self.history.clear();
+    }
+    /// Plays the choice and prints it with the events it caused.
+    fn apply(&mut self, choice: DecodedChoice) {
+        println!("{:?}: {:?}", self.game.state.acting_side(), choice);
+        let ended = self.game.consume_action(choice).is_err();
+        if let Some(n) = self.history.last_mut() {
+            *n += 1;
+        }
+        for event in self.game.take_events() {
+            println!("  {:?}", event);
+        }
+        if ended {
+            let outcome = self.game.outcome().unwrap();
+            match outcome.winner {
+                Some(side) => println!("{:?} wins by {:?}", side, outcome.reason),
+                None => println!("Draw by {:?}", outcome.reason),
+            }
+            println!("VP {:+} on turn {}", outcome.vp, outcome.turn);
+        }
+    }
+    fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(n) => {
+                for _ in 0..n {
+                    self.game.undo();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+    #[cfg(feature = "serde")]
+    fn save(&self, path: &str) -> Result<(), String> {
+        let json = self.game.save().to_json().map_err(|e| format!("{:?}", e))?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+    #[cfg(feature = "serde")]
+    fn load(&mut self, path: &str, seed: u64) -> Result<(), String> {
+        use ts_engine::game::save::SavedGame;
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let saved = SavedGame::from_json(&json).map_err(|e| format!("{:?}", e))?;
+        self.game = Game::load(saved, InternalRand::new_seeded(Stream::Game.seed(seed)));
+        self.start();
+        Ok(())
+    }
+    #[cfg(not(feature = "serde"))]
+    fn save(&self, _path: &str) -> Result<(), String> {
+        Err("Saving needs the serde feature".to_string())
+    }
+    #[cfg(not(feature = "serde"))]
+    fn load(&mut self, _path: &str, _seed: u64) -> Result<(), String> {
+        Err("Loading needs the serde feature".to_string())
+    }
+}
+
+/// Finds the legal actions named by `input`, an optional action followed by
+/// a card or country. Cards are looked up by any unique prefix of their
+/// names, while everything else must be a prefix of one legal action. An
+/// exact name is preferred over longer names it is a prefix of.
+fn find_actions(space: &ActionSpace, legal: &[OutputIndex], input: &str) -> Vec<OutputIndex> {
+    let words: Vec<_> = input
+        .split_whitespace()
+        .map(|w| w.to_ascii_lowercase())
+        .collect();
+    let mut found = Vec::new();
+    let mut exact = Vec::new();
+    for &x in legal.iter() {
+        let decoded = x.decode();
+        let kind = space.range(decoded.action).kind;
+        let name = space.name(x.inner()).unwrap().to_ascii_lowercase();
+        let mut parts = name.splitn(2, ' ');
+        let (action, choice) = (parts.next().unwrap(), parts.next());
+        let (matched, last) = match (words.as_slice(), choice) {
+            ([w], None) => (action.starts_with(w.as_str()), action),
+            ([w], Some(c)) => (names(kind, c, w), c),
+            ([a, w], Some(c)) => (action.starts_with(a.as_str()) && names(kind, c, w), c),
+            _ => (false, action),
+        };
+        if matched {
+            if words.last().map(|w| w.as_str()) == Some(last) {
+                exact.push(x);
+            }
+            found.push(x);
+        }
+    }
+    if found.len() > 1 && !exact.is_empty() {
+        exact
+    } else {
+        found
+    }
+}
+
+/// Whether `word` names the lowercase `choice` of an action.
+fn names(kind: ChoiceKind, choice: &str, word: &str) -> bool {
+    match kind {
+        ChoiceKind::Card => match find_card(word) {
+            Some(card) => choice == format!("{:?}", card).to_ascii_lowercase(),
+            None => choice.starts_with(word),
+        },
+        _ => choice.starts_with(word),
+    }
+}
+
+fn show_legal(legal: &[OutputIndex]) {
+    for (i, x) in legal.iter().enumerate() {
+        println!("{:>3}. {:?}", i + 1, x.decode());
+    }
+}
+
+/// Prints the board as the side sees it, without the opponent's hand.
+fn print_board(state: &GameState, side: Side) {
+    println!("\n{}\n", state.observe(side).state());
+}
+
+fn main() {
+    let config = Config::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!(
+            "Usage: ts-play [--side us|ussr] [--opponent rand|mcts[:N]] [--seed N] [--load FILE]"
+        );
+        process::exit(2);
+    });
+    let mut session = Session::new(&config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    println!(
+        "Playing {:?} against {:?}, seed {}",
+        config.side, config.opponent, config.seed
+    );
+    println!("{}", HELP);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut show = true;
+    // Whether the legal actions are new since they were last listed
+    let mut list = true;
+    loop {
+        let mut legal = Vec::new();
+        if session.game.outcome().is_none() {
+            let side = session.game.state.acting_side();
+            legal = session.game.legal();
+            if legal.is_empty() {
+                session.apply(OutputIndex::pass().decode());
+                list = true;
+                continue;
+            }
+            if side != session.human {
+                let view = session.game.state.observe(side);
+                let choice = session.opponent.decide(&view, legal);
+                session.apply(choice);
+                show = true;
+                continue;
+            }
+            if show {
+                print_board(&session.game.state, session.human);
+            }
+            if show || list {
+                show_legal(&legal);
+            }
+        } else if show {
+            print_board(&session.game.state, session.human);
+            println!("The game is over. Commands: undo, save FILE, load FILE, quit");
+        }
+        show = false;
+        list = false;
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let line = line.trim();
+        let (command, arg) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+        match command {
+            "" => {}
+            "quit" | "exit" => break,
+            "help" => println!("{}", HELP),
+            "board" => show = true,
+            "undo" => {
+                if session.undo() {
+                    show = true;
+                } else {
+                    println!("Nothing to undo");
+                }
+            }
+            "save" | "load" if arg.is_empty() => println!("Usage: {} FILE", command),
+            "save" => match session.save(arg) {
+                Ok(()) => println!("Saved to {}", arg),
+                Err(e) => println!("{}", e),
+            },
+            "load" => match session.load(arg, config.seed) {
+                Ok(()) => show = true,
+                Err(e) => println!("{}", e),
+            },
+            _ if legal.is_empty() => println!("The game is over"),
+            _ => {
+                let found = match line.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= legal.len() => vec![legal[n - 1]],
+                    Ok(_) => Vec::new(),
+                    Err(_) => find_actions(&session.space, &legal, line),
+                };
+                match found.as_slice() {
+                    [x] => {
+                        session.history.push(0);
+                        session.apply(x.decode());
+                        list = true;
+                    }
+                    [] => println!("No legal action matches {:?}, try `help`", line),
+                    _ => {
+                        println!("{:?} could be any of:", line);
+                        for x in found.iter() {
+                            let i = legal.iter().position(|y| y == x).unwrap();
+                            println!("{:>3}. {:?}", i + 1, x.decode());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Plays complete games between two agents and reports how they ended.
+//!
+//! ```text
+//! ts-selfplay [--games N] [--threads N] [--seed N] [--us AGENT] [--ussr AGENT] [--board]
+//! ```
+//!
+//! With `--board` the final board of every game is printed after the summary.
+//!
+//! Agents are `rand` or `mcts[:simulations]`. Game `i` is played with seed
+//! `seed + i`, from which the game RNG and each agent derive their own, so
+//! any panic reported can be reproduced by running that single seed again.
+
+use ts_engine::country::Side;
+use ts_engine::game::{EndReason, Game, RuleSet, Start};
+use ts_engine::harness::{AgentKind, Stream, MAX_PLIES};
+use ts_engine::state::{GameState, InternalRand};
+use ts_engine::tensor::OutputIndex;
+
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+struct Config {
+    games: u64,
+    threads: usize,
+    seed: u64,
+    us: AgentKind,
+    ussr: AgentKind,
+    board: bool,
+}
+
+impl Config {
+    fn from_args() -> Result<Config, String> {
+        let mut config = Config {
+            games: 100,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+            us: AgentKind::Rand,
+            ussr: AgentKind::Rand,
+            board: false,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            if flag == "--board" {
+                config.board = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            let bad = || format!("Bad value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--games" => config.games = value.parse().map_err(|_| bad())?,
+                "--threads" => config.threads = value.parse().map_err(|_| bad())?,
+                "--seed" => config.seed = value.parse().map_err(|_| bad())?,
+                "--us" => config.us = AgentKind::parse(&value).ok_or_else(bad)?,
+                "--ussr" => config.ussr = AgentKind::parse(&value).ok_or_else(bad)?,
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+        config.threads = config.threads.max(1);
+        Ok(config)
+    }
+}
+
+const ENDINGS: [EndReason; 6] = [
+    EndReason::Vp,
+    EndReason::FinalScoring,
+    EndReason::EuropeControl,
+    EndReason::Wargames,
+    EndReason::Defcon,
+    EndReason::HeldScoring,
+];
+
+struct GameResult {
+    seed: u64,
+    /// The winner, or None for a draw or an unfinished game.
+    winner: Option<Side>,
+    /// How the game ended, or None if it was abandoned.
+    ending: Option<EndReason>,
+    turns: i8,
+    plies: usize,
+    /// The final board, if asked for.
+    board: Option<String>,
+}
+
+fn play(config: &Config, seed: u64) -> GameResult {
+    let agents = [
+        config.us.build(Side::US, seed),
+        config.ussr.build(Side::USSR, seed),
+    ];
+    let rng = InternalRand::new_seeded(Stream::Game.seed(seed));
+    let mut game = Game::new(GameState::new(), rng, RuleSet::default());
+    game.setup(Start::Beginning);
+    for plies in 1..=MAX_PLIES {
+        let side = game.state.acting_side();
+        let legal = game.legal();
+        let choice = if legal.is_empty() {
+            OutputIndex::pass().decode()
+        } else {
+            let view = game.state.observe(side);
+            agents[side as usize].decide(&view, legal)
+        };
+        if game.consume_action(choice).is_err() {
+            let outcome = game.outcome().unwrap();
+            return GameResult {
+                seed,
+                winner: outcome.winner,
+                ending: Some(outcome.reason),
+                turns: game.state.turn.min(10),
+                plies,
+                board: board(config, &game.state),
+            };
+        }
+    }
+    GameResult {
+        seed,
+        winner: None,
+        ending: None,
+        turns: game.state.turn,
+        plies: MAX_PLIES,
+        board: board(config, &game.state),
+    }
+}
+
+fn board(config: &Config, state: &GameState) -> Option<String> {
+    if config.board {
+        Some(state.to_string())
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    results: Vec<GameResult>,
+    panics: Vec<(u64, String)>,
+}
+
+fn main() {
+    let config = match Config::from_args() {
+        Ok(c) => Arc::new(c),
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "Usage: ts-selfplay [--games N] [--threads N] [--seed N] \
+                 [--us rand|mcts[:N]] [--ussr rand|mcts[:N]] [--board]"
+            );
+            process::exit(2);
+        }
+    };
+    // Panics are reported with their seed below instead of as they happen
+    panic::set_hook(Box::new(|info| {
+        let msg = info.to_string().replace('\n', " ");
+        LAST_PANIC.with(|p| *p.borrow_mut() = Some(msg));
+    }));
+    let next = Arc::new(AtomicU64::new(0));
+    let summary = Arc::new(Mutex::new(Summary::default()));
+    let handles: Vec<_> = (0..config.threads)
+        .map(|_| {
+            let (config, next, summary) = (config.clone(), next.clone(), summary.clone());
+            thread::spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= config.games {
+                    break;
+                }
+                let seed = config.seed + i;
+                let res = panic::catch_unwind(AssertUnwindSafe(|| play(&config, seed)));
+                let mut summary = summary.lock().unwrap();
+                match res {
+                    Ok(result) => summary.results.push(result),
+                    Err(_) => {
+                        let msg = LAST_PANIC.with(|p| p.borrow_mut().take());
+                        summary.panics.push((seed, msg.unwrap_or_default()));
+                    }
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+    let mut summary = summary.lock().unwrap();
+    summary.panics.sort_by_key(|(seed, _)| *seed);
+    summary.results.sort_by_key(|r| r.seed);
+    report(&config, &summary);
+}
+
+fn report(config: &Config, summary: &Summary) {
+    let results = &summary.results;
+    let finished = results.len().max(1) as f32;
+    println!(
+        "{} games, US {:?} vs USSR {:?}, seeds {}..{}",
+        config.games,
+        config.us,
+        config.ussr,
+        config.seed,
+        config.seed + config.games
+    );
+    println!("{:<14} {:>8} {:>8} {:>8}", "Ending", "US", "USSR", "Total");
+    let endings = ENDINGS.iter().map(|&e| Some(e)).chain(Some(None));
+    for ending in endings {
+        let count = |side: Option<Side>| {
+            results
+                .iter()
+                .filter(|r| r.ending == ending && (side.is_none() || r.winner == side))
+                .count()
+        };
+        let total = count(None);
+        if total == 0 {
+            continue;
+        }
+        let name = ending.map_or("Unfinished".to_string(), |e| format!("{:?}", e));
+        println!(
+            "{:<14} {:>8} {:>8} {:>8}",
+            name,
+            count(Some(Side::US)),
+            count(Some(Side::USSR)),
+            total
+        );
+    }
+    for &side in [Side::US, Side::USSR].iter() {
+        let wins = results.iter().filter(|r| r.winner == Some(side)).count();
+        println!(
+            "{:?} win rate: {:.1}%",
+            side,
+            100.0 * wins as f32 / finished
+        );
+    }
+    let draws = results
+        .iter()
+        .filter(|r| r.ending.is_some() && r.winner.is_none())
+        .count();
+    println!("Draws: {}", draws);
+    let turns: i64 = results.iter().map(|r| r.turns as i64).sum();
+    let plies: usize = results.iter().map(|r| r.plies).sum();
+    println!(
+        "Average length: {:.2} turns, {:.1} actions",
+        turns as f32 / finished,
+        plies as f32 / finished
+    );
+    println!("Panics: {}", summary.panics.len());
+    for (seed, msg) in summary.panics.iter() {
+        println!("  seed {}: {}", seed, msg);
+    }
+    for r in results.iter() {
+        if let Some(board) = r.board.as_ref() {
+            let ending = r
+                .ending
+                .map_or("Unfinished".to_string(), |e| format!("{:?}", e));
+            println!("\nSeed {}, {}:\n{}", r.seed, ending, board);
+        }
+    }
+}
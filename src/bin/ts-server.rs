@@ -0,0 +1,213 @@
+//! Hosts games in which agents in other processes play against the built-in
+//! ones, over the line-delimited JSON protocol of `RemoteAgent`.
+//!
+//! ```text
+//! ts-server [--us AGENT] [--ussr AGENT] [--games N] [--seed N] [--timeout MS] [--listen ADDR]
+//! ```
+//!
+//! Agents are `rand`, `mcts[:simulations]` or `remote`. With `--listen` each
+//! remote side is an agent connecting to the address, the US first if both
+//! are remote. Otherwise a single remote side talks over stdin and stdout,
+//! so the server can be started as a child of the agent's process. The
+//! report goes to stderr either way.
+//!
+//! Every remote agent is greeted once, then told when each game starts, asked
+//! for each of its decisions and told how the game ended. Replies later than
+//! the timeout or not legal are replaced by random legal actions and counted
+//! as faults.
+
+use ts_engine::agent::{Agent, RemoteAgent};
+use ts_engine::country::Side;
+use ts_engine::game::{Game, Start};
+use ts_engine::harness::{AgentKind, Stream, MAX_PLIES};
+use ts_engine::tensor::OutputIndex;
+
+use std::io;
+use std::net::TcpListener;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::time::Duration;
+
+/// Who plays a side: one of the built-in agents or a remote one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Seat {
+    Local(AgentKind),
+    Remote,
+}
+
+impl Seat {
+    fn parse(s: &str) -> Option<Seat> {
+        match s {
+            "remote" => Some(Seat::Remote),
+            _ => AgentKind::parse(s).map(Seat::Local),
+        }
+    }
+}
+
+struct Config {
+    games: u64,
+    seed: u64,
+    timeout: Duration,
+    us: Seat,
+    ussr: Seat,
+    listen: Option<String>,
+}
+
+impl Config {
+    fn from_args() -> Result<Config, String> {
+        let mut config = Config {
+            games: 1,
+            seed: 0,
+            timeout: Duration::from_secs(10),
+            us: Seat::Remote,
+            ussr: Seat::Local(AgentKind::Rand),
+            listen: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", flag))?;
+            let bad = || format!("Bad value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--games" => config.games = value.parse().map_err(|_| bad())?,
+                "--seed" => config.seed = value.parse().map_err(|_| bad())?,
+                "--timeout" => {
+                    config.timeout = Duration::from_millis(value.parse().map_err(|_| bad())?)
+                }
+                "--us" => config.us = Seat::parse(&value).ok_or_else(bad)?,
+                "--ussr" => config.ussr = Seat::parse(&value).ok_or_else(bad)?,
+                "--listen" => config.listen = Some(value),
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+        let remotes = [config.us, config.ussr]
+            .iter()
+            .filter(|&&k| k == Seat::Remote)
+            .count();
+        if remotes == 0 {
+            return Err("At least one side must be remote".to_string());
+        }
+        if remotes == 2 && config.listen.is_none() {
+            return Err("Two remote sides need --listen".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// An agent of either kind, keeping remote agents reachable to report results.
+enum Player {
+    Local(Box<dyn Agent>),
+    Remote(RemoteAgent),
+}
+
+impl Player {
+    fn agent(&self) -> &dyn Agent {
+        match self {
+            Player::Local(agent) => agent.as_ref(),
+            Player::Remote(agent) => agent,
+        }
+    }
+}
+
+fn connect(config: &Config, side: Side, listener: Option<&TcpListener>) -> io::Result<Player> {
+    let seat = match side {
+        Side::US => config.us,
+        _ => config.ussr,
+    };
+    let player = match seat {
+        Seat::Local(kind) => Player::Local(kind.build(side, config.seed)),
+        Seat::Remote => match listener {
+            Some(listener) => {
+                eprintln!(
+                    "Waiting for the {:?} agent on {}",
+                    side,
+                    listener.local_addr()?
+                );
+                let (stream, addr) = listener.accept()?;
+                eprintln!("{:?} agent connected from {}", side, addr);
+                Player::Remote(RemoteAgent::tcp(side, stream, config.timeout)?)
+            }
+            None => Player::Remote(RemoteAgent::stdio(side, config.timeout)?),
+        },
+    };
+    Ok(player)
+}
+
+fn play(players: &[Player; 2], seed: u64) -> Option<String> {
+    for (player, &side) in players.iter().zip(&[Side::US, Side::USSR]) {
+        if let Player::Remote(agent) = player {
+            if let Err(e) = agent.new_game(Stream::Agent(side).seed(seed)) {
+                eprintln!("Could not start the game: {}", e);
+            }
+        }
+    }
+    let mut game = Game::new_seeded(Stream::Game.seed(seed), Start::Beginning);
+    for _ in 0..MAX_PLIES {
+        let side = game.state.acting_side();
+        let legal = game.legal();
+        let choice = if legal.is_empty() {
+            OutputIndex::pass().decode()
+        } else {
+            let view = game.state.observe(side);
+            players[side as usize].agent().decide(&view, legal)
+        };
+        if game.consume_action(choice).is_err() {
+            let outcome = game.outcome().unwrap();
+            for player in players.iter() {
+                if let Player::Remote(agent) = player {
+                    if let Err(e) = agent.game_over(outcome) {
+                        eprintln!("Could not report the result: {}", e);
+                    }
+                }
+            }
+            let winner = outcome
+                .winner
+                .map_or("Draw".to_string(), |s| format!("{:?} wins", s));
+            return Some(format!(
+                "{} by {:?}, VP {:+} on turn {}",
+                winner, outcome.reason, outcome.vp, outcome.turn
+            ));
+        }
+    }
+    None
+}
+
+fn main() {
+    let config = Config::from_args().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!(
+            "Usage: ts-server [--us AGENT] [--ussr AGENT] [--games N] [--seed N] \
+             [--timeout MS] [--listen ADDR]\n\
+             Agents are rand, mcts[:N] or remote"
+        );
+        process::exit(2);
+    });
+    let listener = config.listen.as_ref().map(|addr| {
+        TcpListener::bind(addr).unwrap_or_else(|e| {
+            eprintln!("Could not listen on {}: {}", addr, e);
+            process::exit(1);
+        })
+    });
+    let connect = |side| {
+        connect(&config, side, listener.as_ref()).unwrap_or_else(|e| {
+            eprintln!("Could not connect the {:?} agent: {}", side, e);
+            process::exit(1);
+        })
+    };
+    // Indexed by side, as Side::US is 0
+    let players = [connect(Side::US), connect(Side::USSR)];
+    for i in 0..config.games {
+        let seed = config.seed + i;
+        let result = match panic::catch_unwind(AssertUnwindSafe(|| play(&players, seed))) {
+            Ok(result) => result.unwrap_or_else(|| "Unfinished".to_string()),
+            Err(_) => "Abandoned after an engine panic".to_string(),
+        };
+        eprintln!("Game {} (seed {}): {}", i + 1, seed, result);
+    }
+    for (player, &side) in players.iter().zip([Side::US, Side::USSR].iter()) {
+        if let Player::Remote(agent) = player {
+            eprintln!("{:?} faults: {}", side, agent.faults());
+        }
+    }
+}
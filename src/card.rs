@@ -1,10 +1,13 @@
 #![allow(non_camel_case_types)]
 
-use crate::action::{Action, Allowed, Decision};
+use crate::action::{Action, Allowed, Decision, LazyAllowed};
 use crate::country::{self, CName, Country, Region, Side, Status};
+use crate::game::EndReason;
 use crate::state::{GameState, Period, TwilightRand};
 
 use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 pub mod deck;
 pub mod effect;
@@ -174,6 +177,7 @@ macro_rules! pa {
 }
 
 #[derive(Clone, Copy, PartialEq, FromPrimitive, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Card {
     Dummy = 0,
     Asia_Scoring = 1,
@@ -469,9 +473,9 @@ impl Card {
                         }
                     }
                     if us_roll > ussr_roll {
-                        state.vp += 2;
+                        state.add_vp(2);
                     } else {
-                        state.vp -= 2;
+                        state.add_vp(-2);
                     }
                 } else {
                     state.set_defcon(state.defcon() - 1);
@@ -499,7 +503,7 @@ impl Card {
             }
             South_African_Unrest => {
                 if choice == 0 {
-                    state.countries[CName::SouthAfrica as usize].ussr += 2;
+                    state.change_influence(Side::USSR, CName::SouthAfrica, 2);
                 } else {
                     let allowed = vec![CName::Angola as usize, CName::Botswana as usize];
                     let d = Decision::with_quantity(Side::USSR, Action::Place, allowed, 2);
@@ -510,16 +514,12 @@ impl Card {
                 // The only winning move is not to play
                 if choice != 0 {
                     if let Side::USSR = side {
-                        state.vp += 6;
+                        state.add_vp(6);
                     } else {
-                        state.vp -= 6;
-                    }
-                    // End Game
-                    if state.vp < 0 {
-                        state.vp = -20;
-                    } else {
-                        state.vp = 20;
+                        state.add_vp(-6);
                     }
+                    // The game ends on the VP track as it stands
+                    state.set_end_reason(EndReason::Wargames);
                 }
             }
             _ => unimplemented!(),
@@ -568,10 +568,10 @@ impl Card {
             }
             Duck_and_Cover => {
                 state.set_defcon(state.defcon() - 1);
-                state.vp += 5 - state.defcon();
+                state.add_vp(5 - state.defcon());
             }
             Five_Year_Plan => {
-                let card = state.deck.random_card(Side::USSR, rng);
+                let card = state.random_card(Side::USSR, rng);
                 if let Some(card) = card {
                     if card.att().side == Side::US {
                         let d = Decision::new_event(Side::USSR, card);
@@ -596,14 +596,14 @@ impl Card {
             }
             Vietnam_Revolts => {
                 state.add_effect(Side::USSR, Effect::VietnamRevolts);
-                state.countries[CName::Vietnam as usize].ussr += 2
+                state.change_influence(Side::USSR, CName::Vietnam, 2)
             }
             Korean_War => {
                 let index = CName::SKorea as usize;
                 state.add_mil_ops(Side::USSR, 2);
                 let roll = rng.roll(Side::USSR);
                 if state.war_target(Side::USSR, index, roll) {
-                    state.vp -= 2;
+                    state.add_vp(-2);
                 }
             }
             Romanian_Abdication => {
@@ -619,7 +619,7 @@ impl Card {
                 }
                 state.add_mil_ops(Side::USSR, 2);
                 if state.war_target(Side::USSR, index, roll) {
-                    state.vp -= 2;
+                    state.add_vp(-2);
                 }
             }
             Comecon => {
@@ -633,15 +633,15 @@ impl Card {
                 pa!(state, x);
             }
             Nasser => {
-                let c = &mut state.countries[CName::Egypt as usize];
-                c.ussr += 2;
-                c.us /= 2;
+                let us = state.countries[CName::Egypt as usize].us;
+                state.change_influence(Side::USSR, CName::Egypt, 2);
+                state.set_influence(Side::US, CName::Egypt, us / 2);
             }
             De_Gaulle_Leads_France => {
-                let c = &mut state.countries[CName::France as usize];
-                let remove = std::cmp::min(2, c.us);
-                c.us -= remove;
-                c.ussr += 1;
+                let us = state.countries[CName::France as usize].us;
+                let remove = std::cmp::min(2, us);
+                state.change_influence(Side::US, CName::France, -remove);
+                state.change_influence(Side::USSR, CName::France, 1);
                 state.add_effect(Side::USSR, Effect::DeGaulle);
             }
             Captured_Nazi_Scientist => {
@@ -730,7 +730,7 @@ impl Card {
                     })
                     .collect();
                 if state.has_effect(Side::USSR, Effect::U2) {
-                    state.vp -= 1;
+                    state.add_vp(-1);
                 }
                 let d = Decision::new(side, Action::Ops, vec);
                 pa!(state, d);
@@ -769,8 +769,8 @@ impl Card {
             Nuclear_Test_Ban => {
                 let vps = state.defcon() - 2;
                 match state.side() {
-                    Side::US => state.vp += vps,
-                    Side::USSR => state.vp -= vps,
+                    Side::US => state.add_vp(vps),
+                    Side::USSR => state.add_vp(-vps),
                     _ => unimplemented!(),
                 }
                 state.set_defcon(state.defcon() + 2);
@@ -789,9 +789,9 @@ impl Card {
             Arms_Race => {
                 if state.mil_ops(side) > state.mil_ops(side.opposite()) {
                     if state.mil_ops(side) >= state.defcon() {
-                        state.vp += 3;
+                        state.add_vp(3);
                     } else {
-                        state.vp += 1
+                        state.add_vp(1)
                     }
                 }
             }
@@ -845,10 +845,10 @@ impl Card {
                     })
                     .collect();
                 if us_roll > ussr_roll {
-                    state.vp += 2;
+                    state.add_vp(2);
                     pa!(state, Decision::new(Side::US, Action::ChangeDefcon, defcon));
                 } else if ussr_roll > us_roll {
-                    state.vp -= 2;
+                    state.add_vp(-2);
                     pa!(
                         state,
                         Decision::new(Side::USSR, Action::ChangeDefcon, defcon)
@@ -875,20 +875,20 @@ impl Card {
                 state.set_defcon(state.defcon() - 1);
                 state.add_effect(Side::US, Effect::WWBY);
             }
-            Kitchen_Debates => state.vp += 2,
-            Brezhnev_Doctrine => state.add_effect(Side::US, Effect::Brezhnev),
+            Kitchen_Debates => state.add_vp(2),
+            Brezhnev_Doctrine => state.add_effect(Side::USSR, Effect::Brezhnev),
             Portuguese_Empire_Crumbles => {
-                state.countries[CName::Angola as usize].ussr += 2;
-                state.countries[CName::SEAfricanStates as usize].ussr += 2;
+                state.change_influence(Side::USSR, CName::Angola, 2);
+                state.change_influence(Side::USSR, CName::SEAfricanStates, 2);
             }
-            Allende => state.countries[CName::Chile as usize].ussr += 2,
+            Allende => state.change_influence(Side::USSR, CName::Chile, 2),
             Willy_Brandt => {
-                state.vp -= 1;
-                state.countries[CName::WGermany as usize].ussr += 1;
+                state.add_vp(-1);
+                state.change_influence(Side::USSR, CName::WGermany, 1);
                 state.add_effect(Side::USSR, Effect::WillyBrandt);
             }
             Muslim_Revolution => {
-                let allowed = Allowed::new_lazy(legal::muslim_rev);
+                let allowed = Allowed::new_lazy(LazyAllowed::MuslimRev);
                 let d = Decision::new(Side::USSR, Action::Remove, allowed);
                 pa!(state, d.clone());
                 pa!(state, d);
@@ -900,15 +900,14 @@ impl Card {
             }
             Cultural_Revolution => {
                 if let Side::US = state.deck.china() {
-                    state.deck.play_china();
-                    state.deck.turn_china_up();
+                    state.set_china(Side::USSR, true);
                 } else {
-                    state.vp -= 1;
+                    state.add_vp(-1);
                 }
             }
             Flower_Power => state.add_effect(Side::USSR, Effect::FlowerPower),
             U2_Incident => {
-                state.vp -= 1;
+                state.add_vp(-1);
                 state.add_effect(Side::USSR, Effect::U2);
             }
             OPEC => {
@@ -919,7 +918,7 @@ impl Card {
                         acc
                     }
                 });
-                state.vp -= count;
+                state.add_vp(-count);
             }
             Lone_Gunman => {
                 let ops = self.modified_ops(Side::USSR, state);
@@ -933,15 +932,15 @@ impl Card {
                 pa!(state, x);
             }
             Panama_Canal_Returned => {
-                state.countries[CName::Panama as usize].us += 1;
-                state.countries[CName::CostaRica as usize].us += 1;
-                state.countries[CName::Venezuela as usize].us += 1;
+                state.change_influence(Side::US, CName::Panama, 1);
+                state.change_influence(Side::US, CName::CostaRica, 1);
+                state.change_influence(Side::US, CName::Venezuela, 1);
             }
             Camp_David_Accords => {
-                state.vp += 1;
-                state.countries[CName::Israel as usize].us += 1;
-                state.countries[CName::Jordan as usize].us += 1;
-                state.countries[CName::Egypt as usize].us += 1;
+                state.add_vp(1);
+                state.change_influence(Side::US, CName::Israel, 1);
+                state.change_influence(Side::US, CName::Jordan, 1);
+                state.change_influence(Side::US, CName::Egypt, 1);
                 state.add_effect(Side::US, Effect::CampDavid);
             }
             Puppet_Governments => {
@@ -962,7 +961,7 @@ impl Card {
                 pa!(state, d);
             }
             Grain_Sales => {
-                let hit = rng.card_from_hand(&state.deck, Side::USSR);
+                let hit = state.random_card(Side::USSR, rng);
                 if let Some(card) = hit {
                     let d = Decision::new(
                         Side::US,
@@ -977,9 +976,9 @@ impl Card {
                 }
             }
             John_Paul => {
-                let poland = &mut state.countries[CName::Poland as usize];
-                poland.ussr = std::cmp::max(poland.ussr - 2, 0);
-                poland.us += 1;
+                let ussr = state.countries[CName::Poland as usize].ussr;
+                state.set_influence(Side::USSR, CName::Poland, std::cmp::max(ussr - 2, 0));
+                state.change_influence(Side::US, CName::Poland, 1);
                 state.add_effect(Side::US, Effect::AllowSolidarity);
             }
             Latin_American_Death_Squads => {
@@ -997,15 +996,14 @@ impl Card {
             }
             Nixon_Plays_China => {
                 if let Side::USSR = state.deck.china() {
-                    state.deck.play_china();
+                    state.set_china(Side::US, false);
                 } else {
-                    state.vp += 2;
+                    state.add_vp(2);
                 }
             }
             Sadat_Expels_Soviets => {
-                let egypt = &mut state.countries[CName::Egypt as usize];
-                egypt.ussr = 0;
-                egypt.us += 1;
+                state.remove_all(Side::USSR, CName::Egypt);
+                state.change_influence(Side::US, CName::Egypt, 1);
             }
             Shuttle_Diplomacy => state.add_effect(Side::US, Effect::ShuttleDiplomacy),
             The_Voice_Of_America => {
@@ -1030,8 +1028,7 @@ impl Card {
                     let d = Decision::with_quantity(Side::USSR, Action::Place, legal, 4);
                     pa!(state, d);
                 } else {
-                    state.deck.play_china();
-                    state.deck.turn_china_up();
+                    state.set_china(Side::US, true);
                 }
             }
             Ask_Not => {
@@ -1048,16 +1045,16 @@ impl Card {
                         c.bg && c.controller() == Side::US
                     })
                     .count();
-                state.vp += *count as i8;
+                state.add_vp(*count as i8);
             }
             Africa_Scoring => {
                 Region::Africa.score(state);
             }
             One_Small_Step => {
-                let index = side as usize;
-                if state.space[index] < 7 {
+                let level = state.space[side as usize];
+                if level < 7 {
                     // If you're at space 7, your final location is only +1
-                    state.space[index] += 1;
+                    state.set_space(side, level + 1);
                 }
                 state.space_card(side, 1); // 1 is a perfect roll
             }
@@ -1065,23 +1062,22 @@ impl Card {
                 Region::SouthAmerica.score(state);
             }
             Iranian_Hostage_Crisis => {
-                let iran = &mut state.countries[CName::Iran as usize];
-                iran.us = 0;
-                iran.ussr += 2;
+                state.remove_all(Side::US, CName::Iran);
+                state.change_influence(Side::USSR, CName::Iran, 2);
                 state.add_effect(Side::USSR, Effect::TerrorismPlus);
             }
             The_Iron_Lady => {
-                state.countries[CName::UK as usize].ussr = 0;
-                let arg = &mut state.countries[CName::Argentina as usize];
-                if arg.ussr == 0 {
+                state.remove_all(Side::USSR, CName::UK);
+                if state.countries[CName::Argentina as usize].ussr == 0 {
+                    state.record_flags();
                     state.iron_lady = true; // Flag for the access weirdness
                 }
-                arg.ussr += 1;
-                state.vp += 1;
+                state.change_influence(Side::USSR, CName::Argentina, 1);
+                state.add_vp(1);
                 state.add_effect(Side::US, Effect::IronLady);
             }
             Reagan_Bombs_Libya => {
-                state.vp += state.countries[CName::Libya as usize].ussr / 2;
+                state.add_vp(state.countries[CName::Libya as usize].ussr / 2);
             }
             Star_Wars => {
                 let mut allowed: Vec<_> = state
@@ -1116,14 +1112,14 @@ impl Card {
                 pa!(state, d);
             }
             Marine_Barracks_Bombing => {
-                state.countries[CName::Lebanon as usize].us = 0;
+                state.remove_all(Side::US, CName::Lebanon);
                 let allowed = opp_has_inf(&country::MIDDLE_EAST, Side::USSR, state);
                 let d = Decision::with_quantity(Side::USSR, Action::Remove, allowed, 2);
                 pa!(state, d);
             }
             Soviets_Shoot_Down_KAL => {
                 state.set_defcon(state.defcon() - 1);
-                state.vp += 2;
+                state.add_vp(2);
                 if let Side::US = state.countries[CName::SKorea as usize].controller() {
                     let ops = self.modified_ops(Side::US, state);
                     let d = Decision::conduct_ops(Side::US, ops);
@@ -1132,7 +1128,7 @@ impl Card {
             }
             Glasnost => {
                 state.set_defcon(state.defcon() + 1);
-                state.vp -= 2;
+                state.add_vp(-2);
                 if state.has_effect(Side::USSR, Effect::Reformer) {
                     let ops = self.modified_ops(Side::USSR, state);
                     let d = Decision::conduct_ops(Side::USSR, ops);
@@ -1141,7 +1137,7 @@ impl Card {
             }
             Ortega_Elected => {
                 let nic = CName::Nicaragua as usize;
-                state.countries[nic].us = 0;
+                state.remove_all(Side::US, nic);
                 let allowed = opp_has_inf(&country::EDGES[nic], Side::USSR, state);
                 let ops = self.modified_ops(Side::USSR, state);
                 let d = Decision::with_quantity(Side::USSR, Action::Coup, allowed, ops);
@@ -1149,12 +1145,12 @@ impl Card {
             }
             Terrorism => {
                 let opp = side.opposite();
-                let card = state.deck.random_card(opp, rng);
+                let card = state.random_card(opp, rng);
                 if let Some(c) = card {
                     state.discard_card(opp, c);
                 }
                 if side == Side::USSR && state.has_effect(Side::USSR, Effect::TerrorismPlus) {
-                    let card2 = state.deck.random_card(opp, rng);
+                    let card2 = state.random_card(opp, rng);
                     if let Some(c) = card2 {
                         state.discard_card(opp, c);
                     }
@@ -1167,7 +1163,7 @@ impl Card {
                 pa!(state, d);
             }
             Tear_Down_This_Wall => {
-                state.countries[CName::EGermany as usize].us += 3;
+                state.change_influence(Side::US, CName::EGermany, 3);
                 let ops = self.modified_ops(Side::US, state);
                 let d = Decision::conduct_ops(Side::US, ops);
                 if let Some(i) = state.effect_pos(Side::USSR, Effect::WillyBrandt) {
@@ -1177,7 +1173,7 @@ impl Card {
                 pa!(state, d);
             }
             An_Evil_Empire => {
-                state.vp += 1;
+                state.add_vp(1);
                 if let Some(i) = state.effect_pos(Side::USSR, Effect::FlowerPower) {
                     state.clear_effect(Side::USSR, i);
                 }
@@ -1190,7 +1186,7 @@ impl Card {
                 pa!(state, d);
             }
             Pershing_II_Deployed => {
-                state.vp -= 1;
+                state.add_vp(-1);
                 let allowed: Vec<_> = country::WESTERN_EUROPE
                     .iter()
                     .copied()
@@ -1199,14 +1195,14 @@ impl Card {
                 let d = Decision::with_quantity(Side::USSR, Action::Remove, allowed, 3);
                 pa!(state, d);
             }
-            Solidarity => state.countries[CName::Poland as usize].us += 3,
+            Solidarity => state.change_influence(Side::US, CName::Poland, 3),
             Iran_Iraq_War => {
                 let d = Decision::new(side, Action::War, &country::IRAN_IRAQ[..]);
                 pa!(state, d);
             }
             Defectors => {
                 if *state.side() == Side::USSR && state.ar != 0 {
-                    state.vp += 1
+                    state.add_vp(1)
                 }
             }
             The_Cambridge_Five => {
@@ -1225,7 +1221,7 @@ impl Card {
             Special_Relationship => {
                 // Check for UK control when we see if we can even event the card
                 if state.has_effect(Side::US, Effect::Nato) {
-                    state.vp += 2;
+                    state.add_vp(2);
                     let allowed = &country::WESTERN_EUROPE[..];
                     let d = Decision::new(Side::US, Action::Place, allowed);
                     pa!(state, d);
@@ -1254,7 +1250,7 @@ impl Card {
             }
             Our_Man_In_Tehran => {
                 let mut vec = vec![0];
-                vec.extend(state.deck.our_man(rng).iter().map(|c| *c as usize));
+                vec.extend(state.our_man(rng).iter().map(|c| *c as usize));
                 let len = vec.len() as i8;
                 let d = Decision::with_quantity(Side::US, Action::ChooseCard, vec, len);
                 pa!(state, d);
@@ -1262,7 +1258,7 @@ impl Card {
             Yuri_And_Samantha => state.add_effect(Side::USSR, Effect::Yuri),
             AWACS => {
                 state.add_effect(Side::US, Effect::AWACS);
-                state.countries[CName::SaudiaArabia as usize].us += 2;
+                state.change_influence(Side::US, CName::SaudiaArabia, 2);
             }
             Olympic_Games
             | Blockade
@@ -1309,7 +1305,7 @@ impl Card {
             }
             Willy_Brandt => !state.has_effect(Side::US, Effect::TearDown),
             Muslim_Revolution => !state.has_effect(Side::US, Effect::AWACS),
-            OPEC => !state.has_effect(Side::US, Effect::NoOpec),
+            OPEC => !state.has_effect(Side::USSR, Effect::NoOpec),
             Flower_Power => !state.has_effect(Side::US, Effect::EvilEmpire),
             One_Small_Step => {
                 state.space[eventer as usize] < state.space[eventer.opposite() as usize]
@@ -1333,6 +1329,19 @@ impl Card {
     pub fn is_scoring(&self) -> bool {
         self.att().scoring
     }
+    /// Returns true for the optional cards, which only some rule sets use.
+    pub fn is_optional(&self) -> bool {
+        matches!(
+            self,
+            Card::The_Cambridge_Five
+                | Card::Special_Relationship
+                | Card::NORAD
+                | Card::Che
+                | Card::Our_Man_In_Tehran
+                | Card::Yuri_And_Samantha
+                | Card::AWACS
+        )
+    }
     pub fn scoring_region(&self) -> Option<Region> {
         match self {
             Card::Africa_Scoring => Some(Region::Africa),
@@ -1,7 +1,32 @@
 use super::*;
-use crate::state::TwilightRand;
+use crate::state::{Period, TwilightRand};
 
-#[derive(Clone)]
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A pile of cards in the deck. The China Card is in none of them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pile {
+    Hand(Side),
+    Draw,
+    Discard,
+    PendingDiscard,
+    Removed,
+}
+
+impl Pile {
+    /// Hands and the draw pile are in no particular order, while the other
+    /// piles keep the order cards entered them.
+    fn ordered(self) -> bool {
+        match self {
+            Pile::Hand(_) | Pile::Draw => false,
+            Pile::Discard | Pile::PendingDiscard | Pile::Removed => true,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Deck {
     us_hand: Vec<Card>,
     ussr_hand: Vec<Card>,
@@ -25,7 +50,7 @@ impl Deck {
             china: Side::USSR,
             china_up: true,
         };
-        deck.add_early_war();
+        deck.add_early_war(true);
         deck
     }
     pub fn hand(&self, side: Side) -> &Vec<Card> {
@@ -42,9 +67,56 @@ impl Deck {
             Side::Neutral => unimplemented!(),
         }
     }
-    pub fn end_turn_cleanup(&mut self) {
-        self.china_up = true;
-        // Todo draw cards
+    pub fn pile(&self, pile: Pile) -> &Vec<Card> {
+        match pile {
+            Pile::Hand(side) => self.hand(side),
+            Pile::Draw => &self.draw_pile,
+            Pile::Discard => &self.discard_pile,
+            Pile::PendingDiscard => &self.pending_discard,
+            Pile::Removed => &self.removed,
+        }
+    }
+    pub(crate) fn pile_mut(&mut self, pile: Pile) -> &mut Vec<Card> {
+        match pile {
+            Pile::Hand(side) => self.hand_mut(side),
+            Pile::Draw => &mut self.draw_pile,
+            Pile::Discard => &mut self.discard_pile,
+            Pile::PendingDiscard => &mut self.pending_discard,
+            Pile::Removed => &mut self.removed,
+        }
+    }
+    /// Takes the card out of the pile, returning where it was. In unordered
+    /// piles the top card takes its place.
+    pub fn take(&mut self, pile: Pile, card: Card) -> Result<usize, DeckError> {
+        let vec = self.pile_mut(pile);
+        let index = vec
+            .iter()
+            .position(|&c| c == card)
+            .ok_or(DeckError::CannotFind)?;
+        if pile.ordered() {
+            vec.remove(index);
+        } else {
+            vec.swap_remove(index);
+        }
+        Ok(index)
+    }
+    /// Puts a card back where `take` found it.
+    pub(crate) fn untake(&mut self, pile: Pile, index: usize, card: Card) {
+        let vec = self.pile_mut(pile);
+        if pile.ordered() {
+            vec.insert(index, card);
+        } else {
+            vec.push(card);
+            let last = vec.len() - 1;
+            vec.swap(index, last);
+        }
+    }
+    /// Moves the card to the top of another pile, returning where it was in
+    /// the first.
+    pub fn move_card(&mut self, card: Card, from: Pile, to: Pile) -> Result<usize, DeckError> {
+        let index = self.take(from, card)?;
+        self.pile_mut(to).push(card);
+        Ok(index)
     }
     /// Returns a new vector holding all scoring cards in the side's hand.
     pub fn scoring_cards(&self, side: Side) -> Vec<Card> {
@@ -96,22 +168,11 @@ impl Deck {
             self.draw_to_hand(rng, Side::US);
         }
     }
-    /// Searches the pending discard pile for a played card and removes it.
-    pub fn remove_card(&mut self, card: Card) -> Result<(), DeckError> {
-        let found = self.pending_discard.iter().position(|&c| c == card);
-        if let Some(i) = found {
-            let c = self.pending_discard.swap_remove(i);
-            self.removed.push(c);
-            Ok(())
-        } else {
-            Err(DeckError::CannotFind)
-        }
-    }
     pub fn pending_discard(&self) -> &Vec<Card> {
         &self.pending_discard
     }
-    pub fn flush_pending(&mut self) {
-        self.discard_pile.append(&mut self.pending_discard);
+    pub fn pending_discard_mut(&mut self) -> &mut Vec<Card> {
+        &mut self.pending_discard
     }
     pub fn random_card<T: TwilightRand>(&self, side: Side, rng: &mut T) -> Option<Card> {
         rng.card_from_hand(self, side)
@@ -185,36 +246,16 @@ impl Deck {
     pub fn removed(&self) -> &Vec<Card> {
         &self.removed
     }
+    pub fn removed_mut(&mut self) -> &mut Vec<Card> {
+        &mut self.removed
+    }
     pub fn play_china(&mut self) {
         self.china = self.china.opposite();
         self.china_up = false;
     }
-    pub fn turn_china_up(&mut self) {
-        self.china_up = true;
-    }
-    pub fn play_card(&mut self, side: Side, card: Card) -> Result<(), DeckError> {
-        if let Card::The_China_Card = card {
-            self.play_china();
-            Ok(())
-        } else {
-            // Already ready to be discarded
-            if self.pending_discard.contains(&card) {
-                return Ok(());
-            }
-            match side {
-                Side::US | Side::USSR => {
-                    let hand = self.hand_mut(side);
-                    let index = hand
-                        .iter()
-                        .position(|&c| c == card)
-                        .ok_or(DeckError::CannotFind)?;
-                    let card = hand.swap_remove(index);
-                    self.pending_discard.push(card);
-                    Ok(())
-                }
-                Side::Neutral => Err(DeckError::CannotFind),
-            }
-        }
+    pub fn set_china(&mut self, side: Side, face_up: bool) {
+        self.china = side;
+        self.china_up = face_up;
     }
     pub fn our_man<T: TwilightRand>(&mut self, rng: &mut T) -> &[Card] {
         // Move the 5 cards to the end of the draw pile
@@ -226,61 +267,87 @@ impl Deck {
         self.draw_pile.extend(vec.into_iter());
         &self.draw_pile[self.draw_pile.len() - 5..]
     }
-    pub fn discard_draw(&mut self, card: Card) {
-        let (index, _) = self
-            .draw_pile
-            .iter()
-            .enumerate()
-            .rev()
-            .find(|(_i, c)| **c == card)
-            .expect("Found card");
-        let card = self.draw_pile.swap_remove(index);
-        self.discard_pile.push(card);
-    }
     pub fn china_available(&self, side: Side) -> bool {
         self.china == side && self.china_up
     }
     pub fn china(&self) -> Side {
         self.china
     }
-    pub fn recover_card(&mut self, side: Side, card: Card) {
-        // Todo figure out error handling
-        let index = self.discard_pile.iter().copied().position(|c| c == card);
-        self.discard_pile.swap_remove(index.unwrap());
-        let hand = self.hand_mut(side);
-        hand.push(card);
-    }
     pub fn reset_draw_pile(&mut self) {
         self.draw_pile.append(&mut self.discard_pile);
     }
     pub fn reshuffle<T: TwilightRand>(&mut self, rng: &mut T) {
         rng.reshuffle(self);
     }
-    pub fn add_early_war(&mut self) {
+    /// Returns the cards shuffled into the draw pile at the start of the game,
+    /// i.e. the Early War cards and early optionals less the China Card.
+    pub fn early_war_cards() -> Vec<Card> {
+        let mut vec = Vec::new();
         for c_index in 1..Card::Formosan_Resolution as usize + 1 {
             let card = Card::from_index(c_index);
             if card == Card::The_China_Card {
                 continue;
             }
-            self.draw_pile.push(card);
+            vec.push(card);
         }
         for c_index in Card::Defectors as usize..Card::Che as usize {
-            let card = Card::from_index(c_index);
-            self.draw_pile.push(card);
+            vec.push(Card::from_index(c_index));
         }
+        vec
     }
-    pub fn add_mid_war(&mut self) {
-        // Todo the rest
-        for c_index in Card::Formosan_Resolution as usize + 1..Card::total() {
-            let card = Card::from_index(c_index);
-            self.draw_pile.push(card);
+    /// Returns the cards added to the draw pile at the start of the Mid War.
+    pub fn mid_war_cards() -> Vec<Card> {
+        let mut vec: Vec<_> = (Card::Brush_War as usize..Card::Iranian_Hostage_Crisis as usize)
+            .map(Card::from_index)
+            .collect();
+        vec.extend([Card::Che, Card::Our_Man_In_Tehran].iter());
+        vec
+    }
+    /// Returns the cards added to the draw pile at the start of the Late War.
+    pub fn late_war_cards() -> Vec<Card> {
+        let mut vec: Vec<_> = (Card::Iranian_Hostage_Crisis as usize..Card::Defectors as usize)
+            .map(Card::from_index)
+            .collect();
+        vec.extend([Card::Yuri_And_Samantha, Card::AWACS].iter());
+        vec
+    }
+    /// Returns every card that has entered the deck by the given period,
+    /// leaving out the optional cards if they are not used.
+    pub fn cards_in_play(period: Period, optional_cards: bool) -> Vec<Card> {
+        let mut vec = Self::early_war_cards();
+        if period != Period::Early {
+            vec.extend(Self::mid_war_cards());
         }
+        if period == Period::Late {
+            vec.extend(Self::late_war_cards());
+        }
+        without_optionals(vec, optional_cards)
+    }
+    pub fn add_early_war(&mut self, optional_cards: bool) {
+        let cards = without_optionals(Self::early_war_cards(), optional_cards);
+        self.draw_pile.extend(cards);
     }
-    pub fn add_late_war(&mut self) {
-        todo!()
+    pub fn add_mid_war(&mut self, optional_cards: bool) {
+        let cards = without_optionals(Self::mid_war_cards(), optional_cards);
+        self.draw_pile.extend(cards);
+    }
+    pub fn add_late_war(&mut self, optional_cards: bool) {
+        let cards = without_optionals(Self::late_war_cards(), optional_cards);
+        self.draw_pile.extend(cards);
+    }
+    /// Takes any optional cards still in the draw pile out of the game.
+    pub fn remove_optional_cards(&mut self) {
+        self.draw_pile.retain(|c| !c.is_optional());
     }
 }
 
+fn without_optionals(mut cards: Vec<Card>, optional_cards: bool) -> Vec<Card> {
+    if !optional_cards {
+        cards.retain(|c| !c.is_optional());
+    }
+    cards
+}
+
 #[derive(Debug)]
 pub enum DeckError {
     AlreadyContains,
@@ -313,7 +380,8 @@ mod tests {
         );
         let indices: Vec<_> = cards.iter().map(|c| *c as usize).collect();
         for i in &indices[0..3] {
-            deck.discard_draw(Card::from_index(*i));
+            let card = Card::from_index(*i);
+            deck.move_card(card, Pile::Draw, Pile::Discard).unwrap();
         }
         // Assert discards worked
         assert_eq!(
@@ -327,4 +395,20 @@ mod tests {
             deck.draw_pile.contains(&card);
         }
     }
+    #[test]
+    fn test_periods() {
+        let mut all = Deck::cards_in_play(Period::Late, true);
+        all.sort_by_key(|&c| c as usize);
+        let expected: Vec<_> = (1..Card::total())
+            .map(Card::from_index)
+            .filter(|&c| c != Card::The_China_Card)
+            .collect();
+        assert_eq!(all, expected);
+        let mid = Deck::mid_war_cards();
+        assert!(mid.contains(&Card::South_America_Scoring) && mid.contains(&Card::Che));
+        assert!(!mid.contains(&Card::Iranian_Hostage_Crisis));
+        let late = Deck::late_war_cards();
+        assert_eq!(late.len(), 23);
+        assert!(late.contains(&Card::Iran_Iraq_War) && late.contains(&Card::AWACS));
+    }
 }
@@ -1,6 +1,13 @@
 use crate::country::Side;
 
-#[derive(Clone, Copy, PartialEq)]
+use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub const NUM_EFFECTS: usize = Effect::Yuri as usize + 1;
+
+#[derive(Clone, Copy, PartialEq, FromPrimitive, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Effect {
     ShuttleDiplomacy,
     FormosanResolution,
@@ -44,6 +51,9 @@ pub enum Effect {
 }
 
 impl Effect {
+    pub fn from_index(index: usize) -> Effect {
+        Effect::from_usize(index).unwrap()
+    }
     pub fn allowed_side(&self) -> Side {
         use Effect::*;
         match self {
@@ -1,8 +1,11 @@
 use super::name_index;
 use crate::card::Effect;
+use crate::game::EndReason;
 use crate::state::GameState;
 
 use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 pub const NUM_COUNTRIES: usize = CName::USSR as usize + 1;
@@ -54,6 +57,7 @@ lazy_static! {
 // name_index![2, 3, 4];
 // name_index![3, 5];
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Side {
     US = 0,
     USSR = 1,
@@ -110,7 +114,10 @@ pub fn access(state: &GameState, side: Side) -> Vec<usize> {
     }
     set.remove(&US_INDEX);
     set.remove(&USSR_INDEX);
-    set.into_iter().collect()
+    // Sort so that the order of legal actions does not depend on the hasher
+    let mut vec: Vec<_> = set.into_iter().collect();
+    vec.sort_unstable();
+    vec
 }
 
 fn adjacency_list() -> Vec<Vec<usize>> {
@@ -123,7 +130,8 @@ fn adjacency_list() -> Vec<Vec<usize>> {
     edge_list
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Region {
     Europe,
     WesternEurope,
@@ -306,7 +314,7 @@ impl Region {
         // Usual scoring protocol
         if let Region::SoutheastAsia = self {
             vp_change += bg_diff;
-            state.vp += vp_change;
+            state.add_vp(vp_change);
             return vp_change;
         }
         let us_status = statuses[Side::US as usize];
@@ -315,12 +323,14 @@ impl Region {
         if *self == Europe {
             if let Status::Control = us_status {
                 let x = 20 - state.vp;
-                state.vp = 20;
+                state.set_vp(20);
+                state.set_end_reason(EndReason::EuropeControl);
                 return x;
             }
             if let Status::Control = ussr_status {
                 let x = -20 - state.vp;
-                state.vp = -20;
+                state.set_vp(-20);
+                state.set_end_reason(EndReason::EuropeControl);
                 return x;
             }
         }
@@ -339,7 +349,7 @@ impl Region {
         }
         // 1 point per battleground
         vp_change += bg_diff;
-        state.vp += vp_change;
+        state.add_vp(vp_change);
         return vp_change;
     }
     /// Returns the interval (low, high] for which countries are in the Region.
@@ -368,6 +378,7 @@ impl Region {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Country {
     pub stability: i8,
     pub us: i8,
@@ -427,7 +438,8 @@ impl Country {
     }
 }
 
-#[derive(Clone, Copy, Debug, FromPrimitive)]
+#[derive(Clone, Copy, Debug, PartialEq, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CName {
     Turkey = 0,
     Greece,
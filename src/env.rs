@@ -0,0 +1,224 @@
+//! A gym-style environment for training agents by self-play.
+
+use crate::country::Side;
+use crate::game::{Game, GameOutcome, Start};
+use crate::state::{InternalRand, Win};
+use crate::tensor::{ObservationEncoder, OutputIndex};
+
+mod pool;
+pub use pool::{GamePool, PoolError};
+
+/// How `Env::step` rewards an action, always from the view of
+/// `EnvConfig::side`. The opponent's reward is the negation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reward {
+    /// 1 for a win, -1 for a loss and 0 for a draw, given only when the
+    /// game ends.
+    Terminal,
+    /// The VP change of every action times the scale, plus the terminal
+    /// reward when the game ends.
+    Vp(f32),
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub start: Start,
+    pub reward: Reward,
+    /// The side rewards are given for.
+    pub side: Side,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            start: Start::Beginning,
+            reward: Reward::Terminal,
+            side: Side::US,
+        }
+    }
+}
+
+/// Extra details about a step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepInfo {
+    /// How the game ended, if it did.
+    pub win: Option<Win>,
+    /// The side to act next, whose view the observation is from.
+    pub acting: Side,
+    /// The change in VP, positive for the US.
+    pub vp_change: i8,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnvError {
+    /// The action index is not legal in the current position.
+    Illegal(usize),
+    /// The game is over and must be `reset` first.
+    Finished,
+}
+
+/// A single game stepped by flat `OutputIndex` actions. Observations are
+/// encoded by an `ObservationEncoder` for the side to act.
+pub struct Env {
+    config: EnvConfig,
+    encoder: ObservationEncoder,
+    game: Game<InternalRand>,
+    legal: Vec<OutputIndex>,
+    win: Option<Win>,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Self {
+        let game = Game::new_seeded(0, config.start);
+        let mut env = Env {
+            config,
+            encoder: ObservationEncoder::new(),
+            game,
+            legal: Vec::new(),
+            win: None,
+        };
+        env.update_legal();
+        env
+    }
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+    pub fn game(&self) -> &Game<InternalRand> {
+        &self.game
+    }
+    /// Returns how the game ended, or None while it goes on.
+    pub fn outcome(&self) -> Option<&GameOutcome> {
+        self.game.outcome()
+    }
+    /// Starts a new game seeded by `seed` and returns its first observation.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.game = Game::new_seeded(seed, self.config.start);
+        self.win = None;
+        self.update_legal();
+        self.observation()
+    }
+    /// Plays the action, returning the next observation, the reward, whether
+    /// the game is over and the details of the step.
+    pub fn step(&mut self, action: usize) -> Result<(Vec<f32>, f32, bool, StepInfo), EnvError> {
+        if self.win.is_some() {
+            return Err(EnvError::Finished);
+        }
+        let index = OutputIndex::new(action);
+        if !self.legal.contains(&index) {
+            return Err(EnvError::Illegal(action));
+        }
+        let vp = self.game.state.vp;
+        if let Err(win) = self.game.consume_action(index.decode()) {
+            self.win = Some(win);
+        }
+        let vp_change = self.game.state.vp - vp;
+        self.update_legal();
+        let reward = self.reward(vp_change);
+        let info = StepInfo {
+            win: self.win,
+            acting: self.acting(),
+            vp_change,
+        };
+        Ok((self.observation(), reward, self.win.is_some(), info))
+    }
+    /// Returns a mask over every `OutputIndex`, true for the legal actions.
+    pub fn action_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; OutputIndex::total()];
+        for x in self.legal.iter() {
+            mask[x.inner()] = true;
+        }
+        mask
+    }
+    /// Returns the legal actions. A pass is offered when nothing else is, and
+    /// nothing once the game is over.
+    pub fn legal(&self) -> &[OutputIndex] {
+        &self.legal
+    }
+    /// Encodes the state as seen by the side to act.
+    pub fn observation(&self) -> Vec<f32> {
+        self.encoder.encode(&self.game.state, self.acting())
+    }
+    pub fn observation_size(&self) -> usize {
+        self.encoder.size()
+    }
+    /// Returns the side whose action is awaited.
+    pub fn acting(&self) -> Side {
+        self.game.state.acting_side()
+    }
+    fn update_legal(&mut self) {
+        self.legal = if self.win.is_some() {
+            Vec::new()
+        } else {
+            self.game.legal()
+        };
+        if self.legal.is_empty() && self.win.is_none() {
+            self.legal.push(OutputIndex::pass());
+        }
+    }
+    fn reward(&self, vp_change: i8) -> f32 {
+        reward(&self.config, self.win, vp_change)
+    }
+}
+
+fn reward(config: &EnvConfig, win: Option<Win>, vp_change: i8) -> f32 {
+    let side = config.side;
+    let terminal = match win.map(|w| w.winner()) {
+        Some(s) if s == side => 1.0,
+        Some(Side::Neutral) | None => 0.0,
+        Some(_) => -1.0,
+    };
+    match config.reward {
+        Reward::Terminal => terminal,
+        Reward::Vp(scale) => {
+            let sign = if side == Side::US { 1.0 } else { -1.0 };
+            terminal + sign * scale * vp_change as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_episode() {
+        let config = EnvConfig {
+            reward: Reward::Vp(1.0),
+            side: Side::USSR,
+            ..EnvConfig::default()
+        };
+        let mut env = Env::new(config);
+        let obs = env.reset(2);
+        assert_eq!(obs.len(), env.observation_size());
+        assert_eq!(
+            env.step(OutputIndex::total()),
+            Err(EnvError::Illegal(OutputIndex::total()))
+        );
+        let mut picker = SmallRng::seed_from_u64(2);
+        let mut total = 0.0;
+        let (reward, info) = loop {
+            let mask = env.action_mask();
+            assert_eq!(mask.len(), OutputIndex::total());
+            assert_eq!(mask.iter().filter(|&&x| x).count(), env.legal().len());
+            let legal = env.legal();
+            let action = legal[picker.gen_range(0, legal.len())].inner();
+            let (obs, reward, done, info) = env.step(action).unwrap();
+            assert_eq!(obs.len(), env.observation_size());
+            if done {
+                break (reward, info);
+            }
+            total += reward;
+        };
+        assert!(info.win.is_some());
+        let terminal = env.outcome().unwrap().reward(Side::USSR);
+        // Every VP change was rewarded, negated for the USSR
+        total += reward - terminal;
+        assert_eq!(total, -env.game().state.vp as f32);
+        assert!(env.legal().is_empty());
+        assert_eq!(env.step(0), Err(EnvError::Finished));
+        env.reset(3);
+        assert!(!env.legal().is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use super::{reward, EnvConfig, EnvError};
+use crate::country::Side;
+use crate::game::Game;
+use crate::state::{InternalRand, Win};
+use crate::tensor::{ObservationEncoder, OutputIndex, OutputVec};
+
+/// An action rejected by `GamePool::step`, with the game it was meant for.
+#[derive(Debug, PartialEq)]
+pub struct PoolError {
+    pub game: usize,
+    pub error: EnvError,
+}
+
+struct Slot {
+    game: Game<InternalRand>,
+    /// The seed of the current game. The next one adds the pool's size.
+    seed: u64,
+    legal: OutputVec,
+    reward: f32,
+    win: Option<Win>,
+}
+
+/// A game with its action and its observation and mask rows.
+type Row<'a> = ((&'a mut Slot, &'a usize), (&'a mut [f32], &'a mut [bool]));
+
+/// Many games stepped in lockstep, one action each per step.
+///
+/// Observations and masks are kept in flat buffers, one row per game, that
+/// are overwritten by every step. A game that ends is reset at once, so its
+/// row then describes the new game while `wins` tells how the old one ended.
+/// Game `i` is first seeded `seed + i` and each reset adds the pool's size,
+/// so the games played do not depend on how the steps are scheduled. With
+/// the `rayon` feature the games are stepped in parallel.
+pub struct GamePool {
+    config: EnvConfig,
+    encoder: ObservationEncoder,
+    slots: Vec<Slot>,
+    obs: Vec<f32>,
+    mask: Vec<bool>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+}
+
+impl GamePool {
+    pub fn new(config: EnvConfig, size: usize, seed: u64) -> Self {
+        let encoder = ObservationEncoder::new();
+        let slots = (0..size as u64)
+            .map(|i| Slot {
+                game: Game::new_seeded(seed + i, config.start),
+                seed: seed + i,
+                legal: Vec::new(),
+                reward: 0.0,
+                win: None,
+            })
+            .collect();
+        let mut pool = GamePool {
+            obs: vec![0.0; size * encoder.size()],
+            mask: vec![false; size * OutputIndex::total()],
+            rewards: vec![0.0; size],
+            dones: vec![false; size],
+            config,
+            encoder,
+            slots,
+        };
+        let (obs_len, mask_len) = (pool.obs_size(), OutputIndex::total());
+        let rows = pool
+            .obs
+            .chunks_mut(obs_len)
+            .zip(pool.mask.chunks_mut(mask_len));
+        for (slot, (obs, mask)) in pool.slots.iter_mut().zip(rows) {
+            slot.refresh(&pool.encoder, obs, mask);
+        }
+        pool
+    }
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+    pub fn obs_size(&self) -> usize {
+        self.encoder.size()
+    }
+    /// Plays `actions[i]` in game `i`. Every action is checked before any is
+    /// played, so on an error no game has moved.
+    pub fn step(&mut self, actions: &[usize]) -> Result<(), PoolError> {
+        assert_eq!(actions.len(), self.slots.len());
+        for (i, (slot, &a)) in self.slots.iter().zip(actions).enumerate() {
+            if !slot.legal.contains(&OutputIndex::new(a)) {
+                return Err(PoolError {
+                    game: i,
+                    error: EnvError::Illegal(a),
+                });
+            }
+        }
+        let size = self.slots.len() as u64;
+        let (obs_len, mask_len) = (self.obs_size(), OutputIndex::total());
+        let (config, encoder) = (&self.config, &self.encoder);
+        let step = |((slot, &a), (obs, mask)): Row| slot.step(config, encoder, size, a, obs, mask);
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.slots
+                .par_iter_mut()
+                .zip(actions.par_iter())
+                .zip(
+                    self.obs
+                        .par_chunks_mut(obs_len)
+                        .zip(self.mask.par_chunks_mut(mask_len)),
+                )
+                .for_each(step);
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.slots
+                .iter_mut()
+                .zip(actions.iter())
+                .zip(
+                    self.obs
+                        .chunks_mut(obs_len)
+                        .zip(self.mask.chunks_mut(mask_len)),
+                )
+                .for_each(step);
+        }
+        for (i, slot) in self.slots.iter().enumerate() {
+            self.rewards[i] = slot.reward;
+            self.dones[i] = slot.win.is_some();
+        }
+        Ok(())
+    }
+    /// The observations of all games, `obs_size()` values per game.
+    pub fn observations(&self) -> &[f32] {
+        &self.obs
+    }
+    /// The legal action masks of all games, `OutputIndex::total()` per game.
+    pub fn masks(&self) -> &[bool] {
+        &self.mask
+    }
+    /// The rewards of the last step.
+    pub fn rewards(&self) -> &[f32] {
+        &self.rewards
+    }
+    /// Which games ended in the last step and were reset.
+    pub fn dones(&self) -> &[bool] {
+        &self.dones
+    }
+    /// How each game that ended in the last step was won.
+    pub fn wins(&self) -> impl Iterator<Item = Option<Win>> + '_ {
+        self.slots.iter().map(|s| s.win)
+    }
+    pub fn legal(&self, game: usize) -> &[OutputIndex] {
+        &self.slots[game].legal
+    }
+    /// Returns the side to act in the game, whose view its row is from.
+    pub fn acting(&self, game: usize) -> Side {
+        self.slots[game].game.state.acting_side()
+    }
+    pub fn game(&self, game: usize) -> &Game<InternalRand> {
+        &self.slots[game].game
+    }
+}
+
+impl Slot {
+    fn step(
+        &mut self,
+        config: &EnvConfig,
+        encoder: &ObservationEncoder,
+        size: u64,
+        action: usize,
+        obs: &mut [f32],
+        mask: &mut [bool],
+    ) {
+        let vp = self.game.state.vp;
+        self.win = self
+            .game
+            .consume_action(OutputIndex::new(action).decode())
+            .err();
+        self.reward = reward(config, self.win, self.game.state.vp - vp);
+        if self.win.is_some() {
+            self.seed += size;
+            self.game = Game::new_seeded(self.seed, config.start);
+        }
+        self.refresh(encoder, obs, mask);
+    }
+    /// Updates the legal actions and writes the game's rows.
+    fn refresh(&mut self, encoder: &ObservationEncoder, obs: &mut [f32], mask: &mut [bool]) {
+        // Only the previous legal actions can be set
+        for x in self.legal.iter() {
+            mask[x.inner()] = false;
+        }
+        self.game.legal_into(&mut self.legal);
+        if self.legal.is_empty() {
+            self.legal.push(OutputIndex::pass());
+        }
+        for x in self.legal.iter() {
+            mask[x.inner()] = true;
+        }
+        encoder.encode_into(&self.game.state, self.game.state.acting_side(), obs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::Reward;
+
+    #[test]
+    fn test_pool() {
+        let config = EnvConfig {
+            reward: Reward::Vp(0.1),
+            ..EnvConfig::default()
+        };
+        let mut pool = GamePool::new(config.clone(), 4, 10);
+        let mut other = GamePool::new(config, 4, 10);
+        let total = OutputIndex::total();
+        assert_eq!(pool.observations().len(), 4 * pool.obs_size());
+        assert_eq!(pool.masks().len(), 4 * total);
+        let mut illegal: Vec<_> = (0..4).map(|i| pool.legal(i)[0].inner()).collect();
+        illegal[2] = total;
+        let err = pool.step(&illegal).unwrap_err();
+        assert_eq!(err.game, 2);
+        let mut finished = 0;
+        for ply in 0..20_000 {
+            let actions: Vec<_> = (0..4)
+                .map(|i| {
+                    let legal = pool.legal(i);
+                    legal[ply % legal.len()].inner()
+                })
+                .collect();
+            pool.step(&actions).unwrap();
+            other.step(&actions).unwrap();
+            assert_eq!(pool.observations(), other.observations());
+            for (i, row) in pool.masks().chunks(total).enumerate() {
+                assert_eq!(row.iter().filter(|&&x| x).count(), pool.legal(i).len());
+            }
+            for (done, win) in pool.dones().iter().zip(pool.wins()) {
+                assert_eq!(*done, win.is_some());
+            }
+            finished += pool.dones().iter().filter(|&&x| x).count();
+            if finished >= 2 {
+                break;
+            }
+        }
+        assert!(finished >= 2);
+    }
+}
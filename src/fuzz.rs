@@ -0,0 +1,215 @@
+//! Random walks through legal play, for finding engine panics.
+//!
+//! Every walk is deterministic: the game RNG is seeded from the walk's seed
+//! and each move is picked from `Game::legal`, so a failure is reproduced by
+//! its seed, start point and the picks made. `random_walks` runs many seeds
+//! and shrinks the picks of each failure with `minimize`.
+
+use crate::game::{Game, Start};
+use crate::tensor::OutputIndex;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+
+/// The start points walks are spread over, picked by seed.
+pub const STARTS: [Start; 5] = [
+    Start::Beginning,
+    Start::HL(1),
+    Start::FirstAR(1),
+    Start::HL(4),
+    Start::FirstAR(8),
+];
+
+/// A walk that ended in a panic.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub seed: u64,
+    pub start: Start,
+    /// The moves played, ending with the one that panicked.
+    pub actions: Vec<OutputIndex>,
+    /// The index of each move in the legal moves of its ply. Replayed picks
+    /// wrap around the legal moves, so they stay playable when earlier ones
+    /// are dropped.
+    pub picks: Vec<usize>,
+    pub message: String,
+}
+
+/// Plays at most `max_plies` moves, asking `choose` for the index of each
+/// one among the legal moves, wrapping around. If nothing is legal `choose`
+/// is offered a pass, and the walk stops early if it returns `None` or the
+/// game is won. Returns the moves played.
+pub fn walk<F>(
+    seed: u64,
+    start: Start,
+    max_plies: usize,
+    mut choose: F,
+) -> Result<Vec<OutputIndex>, Failure>
+where
+    F: FnMut(&[OutputIndex]) -> Option<usize>,
+{
+    let mut actions = Vec::new();
+    let mut picks = Vec::new();
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut game = Game::new_seeded(seed, start);
+        while actions.len() < max_plies {
+            let mut legal = game.legal();
+            if legal.is_empty() {
+                legal.push(OutputIndex::pass());
+            }
+            let pick = match choose(&legal) {
+                Some(i) => i,
+                None => return,
+            };
+            let next = legal[pick % legal.len()];
+            actions.push(next);
+            picks.push(pick);
+            if game.consume_action(next.decode()).is_err() {
+                return;
+            }
+        }
+    }));
+    match res {
+        Ok(()) => Ok(actions),
+        Err(payload) => Err(Failure {
+            seed,
+            start,
+            actions,
+            picks,
+            message: panic_message(payload),
+        }),
+    }
+}
+
+/// Walks with moves picked uniformly at random, seeded by `seed`.
+pub fn random_walk(seed: u64, start: Start, max_plies: usize) -> Result<Vec<OutputIndex>, Failure> {
+    let mut picker = SmallRng::seed_from_u64(seed);
+    walk(seed, start, max_plies, |legal| {
+        Some(picker.gen_range(0, legal.len()))
+    })
+}
+
+/// Plays the given picks, as recorded in `Failure::picks`.
+pub fn replay(seed: u64, start: Start, picks: &[usize]) -> Result<Vec<OutputIndex>, Failure> {
+    let mut iter = picks.iter();
+    walk(seed, start, picks.len(), |_| iter.next().copied())
+}
+
+/// Shrinks `items` while `fails` still holds for what is left, first by
+/// dropping chunks of halving size and then single items, until no single
+/// item can be dropped.
+pub fn shrink<T, F>(mut items: Vec<T>, mut fails: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&[T]) -> bool,
+{
+    let mut size = items.len() / 2;
+    while size > 0 {
+        let mut i = 0;
+        let mut dropped = false;
+        while i < items.len() {
+            let mut candidate = items.clone();
+            candidate.drain(i..std::cmp::min(i + size, items.len()));
+            if fails(&candidate) {
+                items = candidate;
+                dropped = true;
+            } else {
+                i += size;
+            }
+        }
+        // Single items are retried until nothing more can go
+        if size > 1 || !dropped {
+            size /= 2;
+        }
+    }
+    items
+}
+
+/// Shrinks the picks of a failure for as long as the rest still panics with
+/// the same message.
+pub fn minimize(failure: Failure) -> Failure {
+    let (seed, start) = (failure.seed, failure.start);
+    let message = failure.message.clone();
+    let picks = shrink(failure.picks.clone(), |picks| {
+        match replay(seed, start, picks) {
+            Err(f) => f.message == message,
+            Ok(_) => false,
+        }
+    });
+    replay(seed, start, &picks).err().unwrap_or(failure)
+}
+
+/// Runs a random walk from each seed, returning the minimized failures.
+pub fn random_walks(seeds: Range<u64>, max_plies: usize) -> Vec<Failure> {
+    seeds
+        .filter_map(|seed| {
+            let start = STARTS[seed as usize % STARTS.len()];
+            random_walk(seed, start, max_plies).err()
+        })
+        .map(minimize)
+        .collect()
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        for &start in STARTS.iter() {
+            let first = random_walk(3, start, 300);
+            let second = random_walk(3, start, 300);
+            match (first, second) {
+                (Ok(x), Ok(y)) => assert_eq!(x, y),
+                (Err(x), Err(y)) => assert_eq!(x.actions, y.actions),
+                _ => panic!("Walks from the same seed differ"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let walk = random_walk(5, Start::HL(1), 300).unwrap();
+        // Picks past the legal moves wrap around to the same ones
+        let mut game = Game::new_seeded(5, Start::HL(1));
+        let mut wrapped = Vec::new();
+        for (n, &x) in walk.iter().enumerate() {
+            let mut legal = game.legal();
+            if legal.is_empty() {
+                legal.push(OutputIndex::pass());
+            }
+            let i = legal.iter().position(|&y| y == x).unwrap();
+            wrapped.push(i + legal.len() * (n % 3));
+            if game.consume_action(x.decode()).is_err() {
+                break;
+            }
+        }
+        assert_eq!(replay(5, Start::HL(1), &wrapped).unwrap(), walk);
+    }
+
+    #[test]
+    fn test_minimize() {
+        // Fails whenever 3, 17 and 18 are all present, in that order
+        let fails = |items: &[u32]| {
+            let i = items.iter().position(|&x| x == 3);
+            let j = items.windows(2).position(|w| w == [17, 18]);
+            matches!((i, j), (Some(i), Some(j)) if i < j)
+        };
+        let items: Vec<u32> = (0..100).collect();
+        assert_eq!(shrink(items, fails), vec![3, 17, 18]);
+        // Dropping any single item passes, so nothing goes
+        let items = vec![3, 17, 18];
+        assert_eq!(shrink(items.clone(), fails), items);
+    }
+}
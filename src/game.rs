@@ -1,40 +1,93 @@
-use crate::action::{Action, Allowed, Decision};
+use crate::action::{Action, Allowed, Decision, LazyAllowed};
 use crate::card::Card;
 use crate::country::Side;
-use crate::state::{GameState, TwilightRand, Win};
+use crate::state::{Delta, GameEvent, GameState, InternalRand, TwilightRand, Win};
 use crate::tensor::{DecodedChoice, OutputIndex, TensorOutput};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod outcome;
 pub mod replay;
+mod rules;
+#[cfg(feature = "serde")]
+pub mod save;
+
+pub use outcome::{EndReason, FinalScore, GameOutcome};
+pub use rules::RuleSet;
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 enum Status {
     Start,
     ChooseHL,
     ResolveHL,
     AR,
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Start {
     Beginning,
     HL(i8),
     FirstAR(i8),
 }
 
+#[derive(Clone)]
 pub struct Game<R: TwilightRand> {
     pub state: GameState,
     pub rng: R,
     ply_history: Vec<DecodedChoice>,
     status: Status,
+    undo_log: Option<Vec<Undo>>,
+    event_log: Option<Vec<GameEvent>>,
+    outcome: Option<GameOutcome>,
+}
+
+/// Everything needed to take back a single consumed action.
+#[derive(Clone)]
+struct Undo {
+    deltas: Vec<Delta>,
+    status: Status,
+    ply_history: Vec<DecodedChoice>,
+    /// The number of events the action added to the event log.
+    events: usize,
 }
 
 impl<R: TwilightRand> Game<R> {
-    pub fn new(state: GameState, rng: R) -> Game<R> {
+    pub fn new(mut state: GameState, rng: R, rules: RuleSet) -> Game<R> {
+        state.set_rules(rules);
+        state.set_recording(false);
         Game {
             state,
             rng,
             ply_history: Vec::new(),
             status: Status::Start,
+            undo_log: None,
+            event_log: None,
+            outcome: None,
         }
     }
+    /// Creates a game from a position already in progress, inferring the
+    /// phase of the turn from the state itself.
+    pub fn resume(state: GameState, rng: R) -> Game<R> {
+        let choosing_hl = state.current_event().is_none()
+            && state
+                .pending()
+                .iter()
+                .any(|d| d.action == Action::ChooseCard);
+        let status = if state.ar != 0 {
+            Status::AR
+        } else if choosing_hl {
+            Status::ChooseHL
+        } else if state.turn == 0 {
+            Status::Start
+        } else {
+            Status::ResolveHL
+        };
+        let rules = state.rules().clone();
+        let mut game = Game::new(state, rng, rules);
+        game.status = status;
+        game
+    }
     pub fn four_four_two(&mut self) {
         use crate::country::CName;
         let c = &mut self.state.countries;
@@ -47,7 +100,7 @@ impl<R: TwilightRand> Game<R> {
     }
     pub fn draw_hands(&mut self) {
         let goal = if self.state.turn <= 3 { 8 } else { 9 };
-        self.state.deck.draw_cards(goal, &mut self.rng);
+        self.state.draw_cards(goal, &mut self.rng);
     }
     pub fn setup(&mut self, start: Start) {
         match start {
@@ -55,6 +108,8 @@ impl<R: TwilightRand> Game<R> {
                 self.status = Status::Start;
                 self.state.turn = 0;
                 self.state.ar = 0;
+                let china = self.state.rules().china_holder;
+                self.state.deck.set_china(china, true);
                 self.draw_hands();
                 self.initial_placement();
             }
@@ -63,7 +118,7 @@ impl<R: TwilightRand> Game<R> {
                 self.state.turn = turn;
                 self.state.ar = 0;
                 self.draw_hands();
-                self.state.set_pending(self.hl_order());
+                self.state.set_pending(self.state.headline_order());
             }
             Start::FirstAR(turn) => {
                 self.status = Status::AR;
@@ -79,10 +134,98 @@ impl<R: TwilightRand> Game<R> {
     pub fn legal(&mut self) -> Vec<OutputIndex> {
         self.state.next_legal()
     }
+    /// Like `legal`, but writes into `legal`, reusing its allocation.
+    pub fn legal_into(&mut self, legal: &mut Vec<OutputIndex>) {
+        self.state.next_legal_into(legal)
+    }
+    /// Turns recording of undo information on or off. Turning it off discards
+    /// any moves recorded so far.
+    pub fn set_undo(&mut self, enabled: bool) {
+        self.undo_log = if enabled { Some(Vec::new()) } else { None };
+        self.state.set_journal(enabled);
+    }
+    /// Takes back the most recently consumed action, returning false if there
+    /// is nothing recorded to undo. The RNG is not rewound. The events of the
+    /// action are taken back too, unless they were already taken.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_log.as_mut().and_then(|log| log.pop()) {
+            Some(undo) => {
+                self.state.revert(undo.deltas);
+                if let Some(log) = self.event_log.as_mut() {
+                    log.truncate(log.len().saturating_sub(undo.events));
+                }
+                self.outcome = None;
+                self.status = undo.status;
+                self.ply_history = undo.ply_history;
+                true
+            }
+            None => false,
+        }
+    }
     /// Consumes an incoming decoded choice from an agent, and resolves until
     /// either the game ends returning an Err(Win) or else until more input
     /// is needed from an agent returning Ok(vp_differential).
     pub fn consume_action(&mut self, decoded: DecodedChoice) -> Result<i8, Win> {
+        if self.undo_log.is_none() && self.event_log.is_none() {
+            let res = self.advance(decoded);
+            self.finish(&res);
+            return res;
+        }
+        let status = self.status;
+        let ply_history = self.ply_history.clone();
+        let res = self.advance(decoded);
+        let mut events = 0;
+        if let Some(log) = self.event_log.as_mut() {
+            let taken = self.state.take_events();
+            events = taken.len();
+            log.extend(taken);
+        }
+        if let Some(undo_log) = self.undo_log.as_mut() {
+            undo_log.push(Undo {
+                deltas: self.state.take_journal(),
+                status,
+                ply_history,
+                events,
+            });
+        }
+        self.finish(&res);
+        res
+    }
+    /// Turns recording of `GameEvent`s on or off. Turning it off discards the
+    /// events recorded so far.
+    pub fn set_event_log(&mut self, enabled: bool) {
+        self.event_log = if enabled { Some(Vec::new()) } else { None };
+        self.state.set_recording(enabled);
+    }
+    /// Returns the events recorded so far, oldest first.
+    pub fn events(&self) -> &[GameEvent] {
+        self.event_log.as_deref().unwrap_or(&[])
+    }
+    /// Takes the events recorded so far, leaving recording on.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        match self.event_log.as_mut() {
+            Some(log) => std::mem::take(log),
+            None => Vec::new(),
+        }
+    }
+    /// Returns how the game ended, or None while it goes on.
+    pub fn outcome(&self) -> Option<&GameOutcome> {
+        self.outcome.as_ref()
+    }
+    /// Records the outcome of a won game. Otherwise panics in debug builds if
+    /// the action left the state inconsistent.
+    fn finish(&mut self, res: &Result<i8, Win>) {
+        if let Err(win) = res {
+            if self.outcome.is_none() {
+                self.outcome = Some(GameOutcome::new(*win, &self.state, None));
+            }
+        } else if cfg!(debug_assertions) {
+            if let Err(e) = self.state.validate() {
+                panic!("Invalid state after action: {:?}", e);
+            }
+        }
+    }
+    fn advance(&mut self, decoded: DecodedChoice) -> Result<i8, Win> {
         let init_vp = self.state.vp;
         // dbg!(&self.status);
         // dbg!(self.state.side);
@@ -91,6 +234,8 @@ impl<R: TwilightRand> Game<R> {
         // dbg!(self.legal());
         // dbg!(&decoded);
         self.consume(decoded);
+        // Reaching 20 VP ends the game at once, before the event has finished
+        self.state.check_win()?;
         self.resolve_neutral()?;
         self.update_status()?;
         if self.state.turn > 10 {
@@ -114,21 +259,23 @@ impl<R: TwilightRand> Game<R> {
                         self.state.order_headlines();
                     }
                     self.status = Status::ResolveHL;
-                    self.state.side = self.state.peek_pending().unwrap().agent;
+                    self.state
+                        .set_side(self.state.peek_pending().unwrap().agent);
                 }
             }
             Status::ResolveHL => {
                 if let Some(pending) = self.state.peek_pending() {
                     if pending.is_single_event() {
                         // Set phasing side
-                        self.state.side = pending.agent;
+                        let agent = pending.agent;
+                        self.state.set_side(agent);
                     }
                 } else {
                     // Enter AR1
                     self.status = Status::AR;
                     self.state.check_win()?;
-                    self.state.ar = 1;
-                    self.state.side = Side::USSR;
+                    self.state.set_ar(1);
+                    self.state.set_side(Side::USSR);
                     self.state.add_pending(Decision::begin_ar(Side::USSR));
                 }
             }
@@ -144,13 +291,15 @@ impl<R: TwilightRand> Game<R> {
                         }
                         // Deck / Hand management
                         if self.state.turn == 4 {
-                            self.state.deck.add_mid_war();
-                        } else if self.state.turn == 7 {
-                            self.state.deck.add_late_war();
+                            self.state.record_piles();
+                            self.state.add_mid_war();
+                        } else if self.state.turn == 8 {
+                            self.state.record_piles();
+                            self.state.add_late_war();
                         }
                         self.draw_hands();
                         self.status = Status::ChooseHL;
-                        self.state.set_pending(self.hl_order());
+                        self.state.set_pending(self.state.headline_order());
                     } else {
                         self.state.add_pending(Decision::begin_ar(self.state.side));
                     }
@@ -159,9 +308,9 @@ impl<R: TwilightRand> Game<R> {
             Status::Start => {
                 if self.state.empty_pending() {
                     // Todo this should need more to be accurate
-                    self.state.turn = 1;
+                    self.state.set_turn(1);
                     self.status = Status::ChooseHL;
-                    self.state.set_pending(self.hl_order());
+                    self.state.set_pending(self.state.headline_order());
                 }
             }
         }
@@ -280,25 +429,11 @@ impl<R: TwilightRand> Game<R> {
             goal
         }
     }
-    fn hl_order(&self) -> Vec<Decision> {
-        let us_hl = Decision::headline(Side::US, &self.state);
-        let ussr_hl = Decision::headline(Side::USSR, &self.state);
-        let us_space = self.state.space[Side::US as usize];
-        let ussr_space = self.state.space[Side::USSR as usize];
-
-        if ussr_space > us_space && ussr_space >= 4 {
-            // If ussr has the power, explicitly make US input their decision first
-            vec![ussr_hl, us_hl]
-        } else {
-            // Else assume as usual that the USSR is phasing first
-            vec![us_hl, ussr_hl]
-        }
-    }
     pub fn standard_start(rng: R) -> Game<R> {
         let state = GameState::four_four_two();
-        let mut game = Game::new(state, rng);
+        let mut game = Game::new(state, rng, RuleSet::default());
         game.status = Status::ChooseHL;
-        game.state.set_pending(game.hl_order());
+        game.state.set_pending(game.state.headline_order());
         game
     }
     fn initial_placement(&mut self) {
@@ -310,9 +445,9 @@ impl<R: TwilightRand> Game<R> {
         // US
         let x = Decision::with_quantity(Side::US, Action::Place, &WESTERN_EUROPE[..], 7);
         pending_actions.push(x);
-        // US Bonus + 2
-        for _ in 0..2 {
-            let allowed = Allowed::new_lazy(legal_bonus_influence);
+        // US Bonus
+        for _ in 0..self.state.rules().bonus_influence {
+            let allowed = Allowed::new_lazy(LazyAllowed::BonusInfluence);
             let d = Decision::new(Side::US, Action::Place, allowed);
             pending_actions.push(d);
         }
@@ -330,25 +465,55 @@ impl<R: TwilightRand> Game<R> {
             SouthAmerica,
             Europe,
         ];
-        for r in order.iter() {
-            r.score(&mut self.state);
+        let regions = order
+            .iter()
+            .map(|r| (*r, r.score(&mut self.state)))
+            .collect();
+        // Europe control wins outright, regardless of the China Card
+        let china = match self.state.deck.china() {
+            _ if self.state.end_reason().is_some() => 0,
+            Side::US => 1,
+            Side::USSR => -1,
+            Side::Neutral => 0,
+        };
+        self.state.add_vp(china);
+        let win = Win::from_vp(self.state.vp);
+        let score = FinalScore { regions, china };
+        self.outcome = Some(GameOutcome::new(win, &self.state, Some(score)));
+        win
+    }
+}
+
+impl Game<InternalRand> {
+    /// Creates a game with the standard rules, set up at the start point and
+    /// with randomness seeded by `seed`. Games set up from a later turn begin
+    /// from a 4-4-2 opening with the Mid and Late War cards added as needed.
+    pub fn new_seeded(seed: u64, start: Start) -> Self {
+        let (state, turn) = match start {
+            Start::Beginning => (GameState::new(), 0),
+            Start::HL(turn) | Start::FirstAR(turn) => (GameState::four_four_two(), turn),
+        };
+        let mut game = Game::new(state, InternalRand::new_seeded(seed), RuleSet::default());
+        if turn >= 4 {
+            game.state.add_mid_war();
         }
-        if self.state.vp >= 0 {
-            Win::Vp(Side::US)
-        } else {
-            Win::Vp(Side::USSR)
+        if turn >= 8 {
+            game.state.add_late_war();
         }
+        game.setup(start);
+        game
     }
 }
 
-fn legal_bonus_influence(state: &GameState) -> Vec<usize> {
+pub(crate) fn legal_bonus_influence(state: &GameState) -> Vec<usize> {
+    let cap = state.rules().bonus_stability_cap;
     state
         .valid_countries()
         .iter()
         .enumerate()
         .filter_map(|(i, x)| {
             // Apparently bonus influence cannot exceed stab + 2
-            if x.us > 0 && x.us < x.stability + 2 {
+            if x.us > 0 && (!cap || x.us < x.stability + 2) {
                 Some(i)
             } else {
                 None
@@ -375,24 +540,137 @@ mod tests {
         game.state.turn = 4;
         game.state.clear_pending();
         game.state.add_pending(Decision::begin_ar(Side::USSR));
+        game.state.add_mid_war();
         game.rng = rng;
         replay
     }
+    /// Moves the card from the draw pile to the side's hand.
+    fn deal(game: &mut Game<DebugRand>, side: Side, card: Card) {
+        let draw = game.state.deck.draw_pile_mut();
+        let index = draw.iter().position(|&c| c == card).unwrap();
+        draw.remove(index);
+        game.state.deck.hand_mut(side).push(card);
+    }
     #[test]
     fn test_summit() {
         let mut replay = get_example_replay();
         let game = &mut replay.game;
-        game.state.deck.us_hand_mut().extend(vec![Card::Summit; 7]);
-        game.state
-            .deck
-            .ussr_hand_mut()
-            .extend(vec![Card::Summit; 7]);
+        deal(game, Side::USSR, Card::Summit);
         let summit_play = DecodedChoice::new(Action::Event, Some(Card::Summit as usize));
         let defcon_one = DecodedChoice::new(Action::ChangeDefcon, Some(1));
         assert!(game.consume_action(summit_play).is_ok());
         assert_eq!(game.consume_action(defcon_one), Err(Win::Defcon(Side::US)));
     }
     #[test]
+    fn test_star_wars() {
+        let mut replay = get_example_replay();
+        let game = &mut replay.game;
+        game.state.side = Side::US;
+        game.state.turn = 8;
+        game.state.clear_pending();
+        game.state.add_pending(Decision::begin_ar(Side::US));
+        game.state.add_late_war();
+        deal(game, Side::US, Card::Star_Wars);
+        deal(game, Side::US, Card::Pershing_II_Deployed);
+        let deck = &mut game.state.deck;
+        deck.us_hand_mut().pop();
+        deck.discard_pile_mut().push(Card::Pershing_II_Deployed);
+        let star_wars = DecodedChoice::new(Action::Event, Some(Card::Star_Wars as usize));
+        let pershing = DecodedChoice::new(Action::Event, Some(Card::Pershing_II_Deployed as usize));
+        game.consume_action(star_wars).unwrap();
+        game.consume_action(pershing).unwrap();
+        let deck = &game.state.deck;
+        assert!(deck.removed().contains(&Card::Star_Wars));
+        assert!(deck.removed().contains(&Card::Pershing_II_Deployed));
+        assert!(!deck.discard_pile().contains(&Card::Pershing_II_Deployed));
+    }
+    #[test]
+    fn test_wargames() {
+        let results = [(6, Win::Draw, None), (7, Win::Vp(Side::US), Some(Side::US))];
+        for &(vp, win, winner) in results.iter() {
+            let mut replay = get_example_replay();
+            let game = &mut replay.game;
+            game.state.side = Side::US;
+            game.state.turn = 8;
+            game.state.vp = vp;
+            game.state.set_defcon(2);
+            game.state.clear_pending();
+            game.state.add_pending(Decision::begin_ar(Side::US));
+            game.state.add_late_war();
+            deal(game, Side::US, Card::Wargames);
+            let wargames = DecodedChoice::new(Action::Event, Some(Card::Wargames as usize));
+            let end = DecodedChoice::new(Action::SpecialEvent, Some(1));
+            game.consume_action(wargames).unwrap();
+            assert_eq!(game.consume_action(end), Err(win));
+            // The USSR gets 6 VP, but the track is otherwise left alone
+            let outcome = game.outcome().unwrap();
+            assert_eq!(outcome.vp, vp - 6);
+            assert_eq!(outcome.reason, EndReason::Wargames);
+            assert_eq!(outcome.winner, winner);
+        }
+    }
+    #[test]
+    fn test_undo() {
+        use crate::country::CName;
+        let mut replay = get_example_replay();
+        let game = &mut replay.game;
+        game.set_undo(true);
+        deal(game, Side::USSR, Card::De_Stalinization);
+        let influence = |game: &Game<DebugRand>| {
+            let c = &game.state.countries;
+            c.iter().map(|x| (x.us, x.ussr)).collect::<Vec<_>>()
+        };
+        let init_inf = influence(game);
+        let init_legal = game.legal();
+        let destal = DecodedChoice::new(Action::Event, Some(Card::De_Stalinization as usize));
+        let remove_fin = DecodedChoice::new(Action::Remove, Some(CName::Finland as usize));
+        game.consume_action(destal).unwrap();
+        let destal_legal = game.legal();
+        game.consume_action(remove_fin).unwrap();
+        assert_ne!(influence(game), init_inf);
+
+        assert!(game.undo());
+        assert_eq!(influence(game), init_inf);
+        assert_eq!(game.legal(), destal_legal);
+        assert!(game.undo());
+        assert_eq!(game.legal(), init_legal);
+        assert_eq!(game.state.deck.ussr_hand(), &vec![Card::De_Stalinization]);
+        assert_eq!(game.state.current_event(), None);
+        assert!(!game.undo());
+    }
+    #[test]
+    fn test_undo_walk() {
+        use crate::fuzz::STARTS;
+        use rand::rngs::SmallRng;
+        use rand::{Rng, SeedableRng};
+        for seed in 0..10 {
+            let mut picker = SmallRng::seed_from_u64(seed);
+            let mut game = Game::new_seeded(seed, STARTS[seed as usize % STARTS.len()]);
+            game.set_undo(true);
+            game.set_event_log(true);
+            for _ in 0..300 {
+                let legal = game.legal();
+                let choice = match legal.len() {
+                    0 => OutputIndex::pass(),
+                    n => legal[picker.gen_range(0, n)],
+                };
+                let before = format!("{:?}", game.state);
+                let events = game.events().len();
+                if game.consume_action(choice.decode()).is_err() {
+                    break;
+                }
+                assert!(game.undo());
+                assert_eq!(format!("{:?}", game.state), before, "Seed {}", seed);
+                assert_eq!(game.events().len(), events);
+                assert_eq!(game.legal(), legal);
+                // The RNG is not rewound, so the action may now end the game
+                if game.consume_action(choice.decode()).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    #[test]
     fn test_destal() {
         use crate::country::CName;
         use std::collections::HashSet;
@@ -418,7 +696,7 @@ mod tests {
             .map(|s| s.to_string())
             .collect();
         let game = &mut replay.game;
-        game.state.deck.ussr_hand_mut().push(Card::De_Stalinization);
+        deal(game, Side::USSR, Card::De_Stalinization);
         let destal = DecodedChoice::new(Action::Event, Some(Card::De_Stalinization as usize));
         game.consume_action(destal).unwrap();
 
@@ -461,4 +739,222 @@ mod tests {
         assert_eq!(Side::US, next.agent);
         assert_eq!(Action::BeginAr, next.action);
     }
+    #[test]
+    fn test_full_game() {
+        use crate::state::{RandEvent, RecordingRand};
+        use rand::rngs::SmallRng;
+        use rand::{Rng, SeedableRng};
+        let rng = RecordingRand::new(InternalRand::new_seeded(2));
+        let mut game = Game::new(GameState::new(), rng, RuleSet::default());
+        game.setup(Start::Beginning);
+        let mut picker = SmallRng::seed_from_u64(2);
+        let mut late_war = false;
+        let mut history = Vec::new();
+        let win = loop {
+            // Random play, except that scoring cards are played at once and
+            // Defcon is left alone, so that the game tends to go the distance
+            let all = game.legal();
+            let calm: Vec<_> = all
+                .iter()
+                .copied()
+                .filter(|x| {
+                    !matches!(
+                        x.decode().action,
+                        Action::Coup | Action::Realignment | Action::War | Action::ChangeDefcon
+                    )
+                })
+                .collect();
+            let scoring: Vec<_> = calm
+                .iter()
+                .copied()
+                .filter(|x| {
+                    let d = x.decode();
+                    d.action == Action::Event && Card::from_index(d.choice.unwrap()).is_scoring()
+                })
+                .collect();
+            let legal = match (scoring.is_empty(), calm.is_empty()) {
+                (false, _) => scoring,
+                (true, false) => calm,
+                (true, true) => all,
+            };
+            let next = legal
+                .get(picker.gen_range(0, legal.len().max(1)))
+                .copied()
+                .unwrap_or(OutputIndex::pass());
+            history.push(next);
+            if let Err(win) = game.consume_action(next.decode()) {
+                break win;
+            }
+            let deck = &game.state.deck;
+            late_war |= deck.us_hand().contains(&Card::Iranian_Hostage_Crisis)
+                || deck.ussr_hand().contains(&Card::Iranian_Hostage_Crisis);
+        };
+        assert_eq!(game.state.turn, 11);
+        assert!(late_war);
+        assert!(matches!(win, Win::Vp(_)));
+
+        // The same game replays with the logged randomness, reshuffles included
+        let log = game.rng.log();
+        assert!(log.iter().any(|e| matches!(e, RandEvent::Reshuffle(_))));
+        let mut replay = Game::new(
+            GameState::new(),
+            game.rng.to_debug_rand(),
+            RuleSet::default(),
+        );
+        replay.setup(Start::Beginning);
+        let mut result = None;
+        for next in history {
+            if let Err(win) = replay.consume_action(next.decode()) {
+                result = Some(win);
+            }
+        }
+        assert_eq!(result, Some(win));
+        assert_eq!(replay.state.vp, game.state.vp);
+        assert_eq!(
+            replay.state.deck.discard_pile(),
+            game.state.deck.discard_pile()
+        );
+    }
+    #[test]
+    fn test_event_log() {
+        use crate::country::CName;
+        use rand::rngs::SmallRng;
+        use rand::{Rng, SeedableRng};
+        let mut game = Game::new_seeded(4, Start::FirstAR(1));
+        game.set_event_log(true);
+        let start = game.state.clone();
+        let mut picker = SmallRng::seed_from_u64(4);
+        for _ in 0..200 {
+            let legal = game.legal();
+            let next = legal
+                .get(picker.gen_range(0, legal.len().max(1)))
+                .copied()
+                .unwrap_or(OutputIndex::pass());
+            if game.consume_action(next.decode()).is_err() {
+                break;
+            }
+        }
+        let events = game.take_events();
+        assert!(events.iter().any(|e| match e {
+            GameEvent::CardPlayed { .. } => true,
+            _ => false,
+        }));
+        // The logged changes add up to the board as it stands
+        let mut influence = vec![[0; 2]; start.countries.len()];
+        let mut vp = start.vp;
+        for e in events.iter() {
+            match *e {
+                GameEvent::Influence {
+                    side,
+                    country,
+                    change,
+                } => influence[country as usize][side as usize] += change,
+                GameEvent::Vp { change, total } => {
+                    vp += change;
+                    assert_eq!(vp, total);
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(vp, game.state.vp);
+        for (i, c) in game.state.countries.iter().enumerate() {
+            let s = &start.countries[i];
+            assert_eq!(influence[i], [c.us - s.us, c.ussr - s.ussr]);
+        }
+        assert!(game.events().is_empty());
+
+        let mut state = GameState::new();
+        state.set_recording(true);
+        state.take_coup(Side::US, CName::Iran as usize, 3, 4, false);
+        let coup = GameEvent::Coup {
+            side: Side::US,
+            country: CName::Iran,
+            roll: 4,
+            ops: 3,
+            result: 3,
+        };
+        let influence = GameEvent::Influence {
+            side: Side::US,
+            country: CName::Iran,
+            change: 3,
+        };
+        let defcon = GameEvent::Defcon { from: 5, to: 4 };
+        // The coup comes first, followed by the changes it made
+        assert_eq!(state.take_events()[..3], [coup, influence, defcon]);
+        assert_eq!(state.mil_ops(Side::US), 3);
+    }
+    #[test]
+    fn test_outcome() {
+        use crate::country::Region;
+        let mut game = Game::new(GameState::new(), DebugRand::new_empty(), RuleSet::default());
+        game.state.turn = 11;
+        game.state.deck.set_china(Side::USSR, true);
+        let mut first = game.clone();
+        let win = first.final_scoring();
+        let outcome = first.outcome().unwrap();
+        let score = outcome.final_score.as_ref().unwrap();
+        assert_eq!(score.china, -1);
+        let total: i8 = score.regions.iter().map(|(_, vp)| vp).sum();
+        assert_eq!(outcome.vp, total + score.china);
+        assert_eq!(outcome.reason, EndReason::FinalScoring);
+        assert_eq!(outcome.winner, Some(win.winner()));
+
+        // A tie on the VP track is a draw
+        game.state.vp = -outcome.vp;
+        assert_eq!(game.clone().final_scoring(), Win::Draw);
+
+        // Europe control wins without the China Card bonus
+        for c in Region::Europe.all_countries() {
+            game.state.countries[c].us = 10;
+        }
+        assert_eq!(game.final_scoring(), Win::Vp(Side::US));
+        let outcome = game.outcome().unwrap();
+        assert_eq!(outcome.reason, EndReason::EuropeControl);
+        assert_eq!(outcome.final_score.as_ref().unwrap().china, 0);
+        assert_eq!(outcome.vp, 20);
+    }
+    #[test]
+    fn test_rule_set() {
+        use crate::country::CName;
+        let rules = RuleSet {
+            optional_cards: false,
+            bonus_influence: 0,
+            china_holder: Side::US,
+            ..RuleSet::default()
+        };
+        let mut game = Game::new(GameState::new(), InternalRand::new_seeded(1), rules);
+        game.setup(Start::Beginning);
+        assert_eq!(game.state.deck.china(), Side::US);
+        assert!(game.state.deck.china_available(Side::US));
+        game.state.add_mid_war();
+        game.state.add_late_war();
+        let deck = &game.state.deck;
+        let cards = deck
+            .us_hand()
+            .iter()
+            .chain(deck.ussr_hand().iter())
+            .chain(deck.draw_pile().iter());
+        // Every card but the Dummy, the China Card and the seven optionals
+        assert_eq!(cards.clone().count(), Card::total() - 9);
+        assert!(cards.clone().all(|c| !c.is_optional()));
+        // Only the USSR and US setup placements remain
+        assert_eq!(game.state.pending().len(), 2);
+
+        let mut state = GameState::new();
+        state.countries[CName::Italy as usize].us = 4;
+        let italy = CName::Italy as usize;
+        assert!(!legal_bonus_influence(&state).contains(&italy));
+        state.set_rules(RuleSet {
+            bonus_stability_cap: false,
+            ..RuleSet::default()
+        });
+        assert!(legal_bonus_influence(&state).contains(&italy));
+
+        let neutral = RuleSet {
+            china_holder: Side::Neutral,
+            ..RuleSet::default()
+        };
+        assert!(!neutral.is_valid());
+        assert!(RuleSet::default().is_valid());
+    }
 }
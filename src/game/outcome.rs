@@ -0,0 +1,78 @@
+use crate::country::{Region, Side};
+use crate::state::{GameState, Win};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EndReason {
+    /// The VP track reached 20 during play.
+    Vp,
+    /// A side controlled Europe when it was scored.
+    EuropeControl,
+    /// Wargames was played to end the game.
+    Wargames,
+    /// Final scoring after turn 10 decided the game.
+    FinalScoring,
+    Defcon,
+    /// A scoring card was still held at the end of a turn.
+    HeldScoring,
+}
+
+/// The VP change of each step of final scoring, positive for the US.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FinalScore {
+    pub regions: Vec<(Region, i8)>,
+    /// The VP for holding the China Card.
+    pub china: i8,
+}
+
+/// How a game ended, in more detail than `Win`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameOutcome {
+    /// The winning side, or None for a draw.
+    pub winner: Option<Side>,
+    pub reason: EndReason,
+    pub vp: i8,
+    pub turn: i8,
+    /// The breakdown of final scoring, if the game got that far.
+    pub final_score: Option<FinalScore>,
+}
+
+impl GameOutcome {
+    pub fn new(win: Win, state: &GameState, final_score: Option<FinalScore>) -> Self {
+        let reason = match win {
+            Win::Defcon(_) => EndReason::Defcon,
+            Win::HeldScoring(_) => EndReason::HeldScoring,
+            Win::Vp(_) | Win::Draw => match state.end_reason() {
+                Some(r) => r,
+                None if final_score.is_some() => EndReason::FinalScoring,
+                None => EndReason::Vp,
+            },
+        };
+        let winner = match win.winner() {
+            Side::Neutral => None,
+            side => Some(side),
+        };
+        GameOutcome {
+            winner,
+            reason,
+            vp: state.vp,
+            turn: state.turn,
+            final_score,
+        }
+    }
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none()
+    }
+    /// Returns 1 if the side won, -1 if it lost and 0 for a draw.
+    pub fn reward(&self, side: Side) -> f32 {
+        match self.winner {
+            Some(s) if s == side => 1.0,
+            Some(_) => -1.0,
+            None => 0.0,
+        }
+    }
+}
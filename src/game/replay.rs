@@ -1,6 +1,7 @@
 use super::{Game, Start, Win};
 use crate::agent::{Agent, ScriptedAgent};
 use crate::country::Side;
+use crate::record::RecordError;
 use crate::state::DebugRand;
 use crate::state::GameState;
 use crate::tensor::{DecodedChoice, OutputIndex, TensorOutput};
@@ -33,9 +34,15 @@ impl Replay {
     pub fn add_check(&mut self, check: Box<dyn Fn(&Self)>) {
         self.checks.push(check);
     }
-    pub fn play(&mut self, start: Start) -> Option<Win> {
+    /// Plays the scripted choices, returning how the game ended if it did.
+    /// Fails if a card the record draws or reshuffles is not in the draw
+    /// pile.
+    pub fn play(&mut self, start: Start) -> Result<Option<Win>, RecordError> {
         self.checks.reverse();
         self.game.setup(start);
+        if let Some(e) = self.game.rng.error.take() {
+            return Err(e);
+        }
         while self.us_agent.choices.lock().unwrap().len() > 0
             || self.ussr_agent.choices.lock().unwrap().len() > 0
         {
@@ -63,16 +70,20 @@ impl Replay {
                 }
                 ret
             } else {
+                let view = self.game.state.observe(next.agent);
                 let legal = self.game.legal();
-                let ret = agent.decide(&self.game.state, legal);
+                let ret = agent.decide(&view, legal);
                 self.history.push(ret.clone());
                 ret
             };
             let res = self.game.consume_action(decoded);
+            if let Some(e) = self.game.rng.error.take() {
+                return Err(e);
+            }
             if let Err(win) = res {
-                return Some(win);
+                return Ok(Some(win));
             }
         }
-        None
+        Ok(None)
     }
 }
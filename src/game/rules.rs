@@ -0,0 +1,39 @@
+use crate::country::Side;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The rule variants a game is played with. The default is the standard
+/// game with the optional cards and the tournament bonus influence.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RuleSet {
+    /// Whether the optional cards, `The_Cambridge_Five` through `AWACS`, are
+    /// shuffled into the deck.
+    pub optional_cards: bool,
+    /// Extra influence the US places after the initial setup.
+    pub bonus_influence: i8,
+    /// The side holding the China Card, face up, at the start of the game.
+    pub china_holder: Side,
+    /// Whether bonus influence must stay below stability + 2 in a country.
+    pub bonus_stability_cap: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            optional_cards: true,
+            bonus_influence: 2,
+            china_holder: Side::USSR,
+            bonus_stability_cap: true,
+        }
+    }
+}
+
+impl RuleSet {
+    /// Whether the rules can be played, which needs the China Card to start
+    /// with the US or the USSR.
+    pub fn is_valid(&self) -> bool {
+        self.china_holder != Side::Neutral
+    }
+}
@@ -0,0 +1,122 @@
+use super::{Game, Status};
+use crate::state::{GameState, TwilightRand};
+use crate::tensor::DecodedChoice;
+
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever the saved representation changes, so that old saves are
+/// rejected instead of being misread.
+pub const SAVE_VERSION: u32 = 1;
+
+/// A checkpoint of a game in progress. The RNG is not included, so the
+/// caller decides how randomness continues after loading.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    version: u32,
+    state: GameState,
+    status: Status,
+    ply_history: Vec<DecodedChoice>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Version(u32),
+    /// The saved rules cannot be played.
+    Rules,
+    Json(serde_json::Error),
+    Bincode(bincode::Error),
+}
+
+impl SavedGame {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        serde_json::to_string(self).map_err(SaveError::Json)
+    }
+    pub fn from_json(s: &str) -> Result<Self, SaveError> {
+        let saved: SavedGame = serde_json::from_str(s).map_err(SaveError::Json)?;
+        saved.check_version()
+    }
+    pub fn to_bincode(&self) -> Result<Vec<u8>, SaveError> {
+        bincode::serialize(self).map_err(SaveError::Bincode)
+    }
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, SaveError> {
+        let saved: SavedGame = bincode::deserialize(bytes).map_err(SaveError::Bincode)?;
+        saved.check_version()
+    }
+    fn check_version(self) -> Result<Self, SaveError> {
+        if self.version != SAVE_VERSION {
+            Err(SaveError::Version(self.version))
+        } else if !self.state.rules().is_valid() {
+            Err(SaveError::Rules)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl<R: TwilightRand> Game<R> {
+    /// Returns a checkpoint of the game. Undo information is not saved.
+    pub fn save(&self) -> SavedGame {
+        SavedGame {
+            version: SAVE_VERSION,
+            state: self.state.clone(),
+            status: self.status,
+            ply_history: self.ply_history.clone(),
+        }
+    }
+    /// Restores a game from a checkpoint, continuing with the given RNG.
+    pub fn load(saved: SavedGame, rng: R) -> Game<R> {
+        let rules = saved.state.rules().clone();
+        let mut game = Game::new(saved.state, rng, rules);
+        game.status = saved.status;
+        game.ply_history = saved.ply_history;
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{RuleSet, Start};
+    use crate::state::InternalRand;
+
+    #[test]
+    fn test_round_trip() {
+        let mut game = Game::new(
+            GameState::new(),
+            InternalRand::new_seeded(5),
+            RuleSet::default(),
+        );
+        game.setup(Start::Beginning);
+        // Play out the initial placement up to the lazy bonus influence
+        for _ in 0..13 {
+            let next = game.legal()[0];
+            game.consume_action(next.decode()).unwrap();
+        }
+        let saved = game.save();
+        let json = saved.to_json().unwrap();
+        assert!(json.contains("bonus_influence"));
+        let json = SavedGame::from_json(&json).unwrap();
+        let bin = SavedGame::from_bincode(&saved.to_bincode().unwrap()).unwrap();
+        for loaded in [json, bin].iter() {
+            let mut copy = Game::load(loaded.clone(), InternalRand::new_seeded(5));
+            assert_eq!(copy.legal(), game.legal());
+            assert_eq!(copy.state.deck.us_hand(), game.state.deck.us_hand());
+            assert_eq!(copy.state.vp, game.state.vp);
+            let inf = |g: &Game<InternalRand>| {
+                let c = &g.state.countries;
+                c.iter().map(|x| (x.us, x.ussr)).collect::<Vec<_>>()
+            };
+            assert_eq!(inf(&copy), inf(&game));
+        }
+        let mut stale = saved.clone();
+        stale.version = 0;
+        let json = stale.to_json().unwrap();
+        assert!(SavedGame::from_json(&json).is_err());
+    }
+}
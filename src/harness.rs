@@ -0,0 +1,116 @@
+//! Pieces shared by the binaries that pit agents against each other.
+
+use crate::agent::{Agent, MctsAgent, MctsConfig, RandAgent, UniformPolicy};
+use crate::country::Side;
+
+/// Games still running after this many actions are abandoned.
+pub const MAX_PLIES: usize = 20_000;
+
+/// The random streams of a game played from a single seed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    /// Dice rolls and card draws.
+    Game,
+    /// A side's agent.
+    Agent(Side),
+    /// The rollouts of a side's search agent.
+    Rollout(Side),
+}
+
+impl Stream {
+    /// Derives this stream's seed from the game's, so that every stream can
+    /// be reproduced from one seed without any two of them moving in step.
+    pub fn seed(self, seed: u64) -> u64 {
+        let role = match self {
+            Stream::Game => 1,
+            Stream::Agent(Side::US) => 2,
+            Stream::Agent(_) => 3,
+            Stream::Rollout(Side::US) => 4,
+            Stream::Rollout(_) => 5,
+        };
+        splitmix(seed ^ splitmix(role))
+    }
+}
+
+/// The SplitMix64 finalizer, which scatters nearby inputs across the range.
+fn splitmix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A built-in agent, as named on the command line: `rand` or
+/// `mcts[:simulations]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AgentKind {
+    Rand,
+    Mcts(usize),
+}
+
+impl AgentKind {
+    pub fn parse(s: &str) -> Option<AgentKind> {
+        let mut split = s.splitn(2, ':');
+        match (split.next()?, split.next()) {
+            ("rand", None) => Some(AgentKind::Rand),
+            ("mcts", None) => Some(AgentKind::Mcts(MctsConfig::default().simulations)),
+            ("mcts", Some(n)) => n.parse().ok().map(AgentKind::Mcts),
+            _ => None,
+        }
+    }
+    /// Builds the agent for `side` of the game played with `seed`.
+    pub fn build(self, side: Side, seed: u64) -> Box<dyn Agent> {
+        let agent_seed = Stream::Agent(side).seed(seed);
+        match self {
+            AgentKind::Rand => Box::new(RandAgent::new_seeded(agent_seed)),
+            AgentKind::Mcts(simulations) => {
+                let config = MctsConfig {
+                    simulations,
+                    ..MctsConfig::default()
+                };
+                let rollout = RandAgent::new_seeded(Stream::Rollout(side).seed(seed));
+                Box::new(MctsAgent::new(
+                    side,
+                    rollout,
+                    UniformPolicy,
+                    config,
+                    agent_seed,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let simulations = MctsConfig::default().simulations;
+        assert_eq!(AgentKind::parse("rand"), Some(AgentKind::Rand));
+        assert_eq!(AgentKind::parse("mcts"), Some(AgentKind::Mcts(simulations)));
+        assert_eq!(AgentKind::parse("mcts:50"), Some(AgentKind::Mcts(50)));
+        assert_eq!(AgentKind::parse("mcts:many"), None);
+        assert_eq!(AgentKind::parse("rand:1"), None);
+        assert_eq!(AgentKind::parse("remote"), None);
+    }
+
+    #[test]
+    fn test_stream_seeds() {
+        let streams = [
+            Stream::Game,
+            Stream::Agent(Side::US),
+            Stream::Agent(Side::USSR),
+            Stream::Rollout(Side::US),
+            Stream::Rollout(Side::USSR),
+        ];
+        for seed in 0..4 {
+            let seeds: Vec<_> = streams.iter().map(|s| s.seed(seed)).collect();
+            for (i, x) in seeds.iter().enumerate() {
+                assert!(seeds[i + 1..].iter().all(|y| x != y));
+                assert_ne!(*x, seed);
+            }
+        }
+    }
+}
@@ -8,10 +8,15 @@ pub mod action;
 pub mod agent;
 pub mod card;
 pub mod country;
+pub mod env;
+pub mod fuzz;
 pub mod game;
+pub mod harness;
+#[cfg(feature = "python")]
+pub mod python;
 pub mod record;
 pub mod state;
-mod tensor;
+pub mod tensor;
 
 #[macro_export]
 #[doc(hidden)]
@@ -0,0 +1,208 @@
+//! Python bindings, enabled with the `python` feature.
+//!
+//! Build the extension with `maturin develop --release`, then:
+//!
+//! ```text
+//! import ts_engine
+//! game = ts_engine.Game(seed=1)
+//! while not game.done:
+//!     legal = game.legal()
+//!     game.step(int(legal[0]))
+//! print(game.winner, game.reward("US"))
+//! ```
+
+use crate::country::Side;
+use crate::game::{Game, Start};
+use crate::state::InternalRand;
+use crate::tensor::observation::ObservationEncoder;
+use crate::tensor::OutputIndex;
+
+use numpy::{PyArray1, ToPyArray};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+/// A game driven by flat `OutputIndex` actions.
+#[pyclass(name = "Game")]
+#[derive(Clone)]
+struct PyGame {
+    game: Game<InternalRand>,
+}
+
+#[pymethods]
+impl PyGame {
+    /// Creates a game seeded by `seed`. The start is "beginning", "hl" or
+    /// "first_ar", the latter two from the given turn.
+    #[new]
+    #[pyo3(signature = (seed, start = "beginning", turn = 1))]
+    fn new(seed: u64, start: &str, turn: i8) -> PyResult<Self> {
+        if start != "beginning" && !(1..=10).contains(&turn) {
+            return Err(PyValueError::new_err(format!("Bad turn {}", turn)));
+        }
+        let start = match start {
+            "beginning" => Start::Beginning,
+            "hl" => Start::HL(turn),
+            "first_ar" => Start::FirstAR(turn),
+            _ => return Err(PyValueError::new_err(format!("Unknown start {}", start))),
+        };
+        Ok(PyGame {
+            game: Game::new_seeded(seed, start),
+        })
+    }
+    /// Returns the legal actions as flat indices. A pass is offered when
+    /// nothing else is legal.
+    fn legal<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray1<u64>> {
+        self.legal_indices().to_pyarray(py)
+    }
+    /// Plays the action and returns the change in VP it caused, positive
+    /// for the US.
+    fn step(&mut self, action: usize) -> PyResult<i8> {
+        if self.done() {
+            return Err(PyRuntimeError::new_err("The game is over"));
+        }
+        if !self.legal_indices().contains(&(action as u64)) {
+            return Err(PyValueError::new_err(format!("Illegal action {}", action)));
+        }
+        let vp = self.game.state.vp;
+        match self.game.consume_action(OutputIndex::new(action).decode()) {
+            Ok(delta) => Ok(delta),
+            Err(_) => Ok(self.game.state.vp - vp),
+        }
+    }
+    /// Encodes the state as seen by the side, by default the one to act.
+    #[pyo3(signature = (side = None))]
+    fn observation<'py>(
+        &self,
+        py: Python<'py>,
+        side: Option<&str>,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        let side = match side {
+            Some(s) => parse_side(s)?,
+            None => self.game.state.acting_side(),
+        };
+        let obs = ObservationEncoder::new().encode(&self.game.state, side);
+        Ok(obs.to_pyarray(py))
+    }
+    /// Returns 1 if the side has won, -1 if it has lost and 0 otherwise.
+    fn reward(&self, side: &str) -> PyResult<f32> {
+        let side = parse_side(side)?;
+        Ok(self.game.outcome().map_or(0.0, |o| o.reward(side)))
+    }
+    #[getter]
+    fn done(&self) -> bool {
+        self.game.outcome().is_some()
+    }
+    /// The winning side, "Draw", or None while the game goes on.
+    #[getter]
+    fn winner(&self) -> Option<String> {
+        self.game.outcome().map(|o| match o.winner {
+            Some(side) => format!("{:?}", side),
+            None => "Draw".to_string(),
+        })
+    }
+    /// Why the game ended, e.g. "EuropeControl", or None while it goes on.
+    #[getter]
+    fn end_reason(&self) -> Option<String> {
+        self.game.outcome().map(|o| format!("{:?}", o.reason))
+    }
+    /// The side whose action is awaited.
+    #[getter]
+    fn acting_side(&self) -> String {
+        format!("{:?}", self.game.state.acting_side())
+    }
+    #[getter]
+    fn vp(&self) -> i8 {
+        self.game.state.vp
+    }
+    #[getter]
+    fn turn(&self) -> i8 {
+        self.game.state.turn
+    }
+    #[getter]
+    fn ar(&self) -> i8 {
+        self.game.state.ar
+    }
+    #[staticmethod]
+    fn observation_size() -> usize {
+        ObservationEncoder::new().size()
+    }
+    fn clone(&self) -> Self {
+        Clone::clone(self)
+    }
+    fn __copy__(&self) -> Self {
+        Clone::clone(self)
+    }
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        Clone::clone(self)
+    }
+}
+
+impl PyGame {
+    fn legal_indices(&mut self) -> Vec<u64> {
+        if self.done() {
+            return Vec::new();
+        }
+        let legal = self.game.legal();
+        if legal.is_empty() {
+            vec![OutputIndex::pass().inner() as u64]
+        } else {
+            legal.iter().map(|x| x.inner() as u64).collect()
+        }
+    }
+}
+
+fn parse_side(s: &str) -> PyResult<Side> {
+    match s {
+        "US" => Ok(Side::US),
+        "USSR" => Ok(Side::USSR),
+        _ => Err(PyValueError::new_err(format!("Unknown side {}", s))),
+    }
+}
+
+#[pymodule]
+fn ts_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert!(PyGame::new(1, "beginning", 1).is_ok());
+        assert!(PyGame::new(1, "hl", 4).is_ok());
+        assert!(PyGame::new(1, "first_ar", 0).is_err());
+        assert!(PyGame::new(1, "middle", 1).is_err());
+    }
+
+    #[test]
+    fn test_play() {
+        let mut game = PyGame::new(3, "beginning", 1).unwrap();
+        assert!(game.step(usize::MAX).is_err());
+        assert!(game.reward("Neutral").is_err());
+        let mut plies = 0;
+        while !game.done() {
+            let legal = game.legal_indices();
+            let action = legal[plies % legal.len()] as usize;
+            if plies == 100 {
+                // Copies play on without touching the original
+                let mut copy = game.clone();
+                copy.step(action).unwrap();
+                assert_eq!(game.legal_indices(), legal);
+            }
+            game.step(action).unwrap();
+            plies += 1;
+        }
+        assert!(game.legal_indices().is_empty());
+        assert!(game.step(OutputIndex::pass().inner()).is_err());
+        assert!(game.end_reason().is_some());
+        let (us, ussr) = (game.reward("US").unwrap(), game.reward("USSR").unwrap());
+        assert_eq!(us, -ussr);
+        match game.winner().unwrap().as_str() {
+            "US" => assert_eq!(us, 1.0),
+            "USSR" => assert_eq!(ussr, 1.0),
+            _ => assert_eq!(us, 0.0),
+        }
+    }
+}
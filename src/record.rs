@@ -3,7 +3,7 @@ use crate::agent::ScriptedAgent;
 use crate::card::Card;
 use crate::country::{CName, Side};
 use crate::game::replay::Replay;
-use crate::game::Game;
+use crate::game::{Game, RuleSet};
 use crate::state::{DebugRand, GameState};
 use crate::tensor::OutputIndex;
 use nom::{
@@ -18,6 +18,9 @@ use nom::{
 use radix_trie::Trie;
 use std::collections::HashMap;
 
+mod writer;
+pub use writer::RecordWriter;
+
 lazy_static! {
     static ref CARDS: Trie<String, Card> = {
         let mut trie = Trie::new();
@@ -28,7 +31,7 @@ lazy_static! {
         trie
     };
     static ref COUNTRIES: HashMap<String, CName> = {
-        (1..CName::total())
+        (0..CName::total())
             .map(|i| {
                 let c = CName::from_index(i);
                 (format!("{:?}", c), c)
@@ -37,13 +40,72 @@ lazy_static! {
     };
 }
 
-// Todo real error handling?
 macro_rules! become_err {
     ($e:expr) => {
         Err(Error(($e, ErrorKind::Fix)))
     };
 }
 
+/// Why a line of a record could not be parsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordErrorKind {
+    /// No card name starts with the given prefix.
+    UnknownCard,
+    /// More than one card name starts with the given prefix.
+    AmbiguousCard,
+    /// A choice is neither a number nor a country name.
+    UnknownCountry,
+    /// The word in the action position is not a known keyword.
+    UnknownKeyword,
+    /// The action needs a card, but none was given.
+    MissingCard,
+    /// The action needs choices, but none were given.
+    MissingChoices,
+    /// The line has neither a card nor an action.
+    MissingAction,
+    /// A card drawn or reshuffled into place is not in the draw pile when
+    /// the record is replayed.
+    NotInDrawPile,
+    /// The choice has no form in the record format, so it cannot be written.
+    UnsupportedChoice,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordError {
+    /// Line number, starting from 1, or 0 if not from a record.
+    pub line: usize,
+    /// Column of the offending token, starting from 1, or 0 if not from a
+    /// record.
+    pub column: usize,
+    /// The offending token, or empty if something is missing at the end of
+    /// the line.
+    pub token: String,
+    pub reason: RecordErrorKind,
+}
+
+impl RecordError {
+    /// Creates an error for the token starting at `at`, a subslice of `line`.
+    fn new(number: usize, line: &str, at: &str, reason: RecordErrorKind) -> Self {
+        let offset = at.as_ptr() as usize - line.as_ptr() as usize;
+        RecordError {
+            line: number,
+            column: offset + 1,
+            token: next_word(at).unwrap_or("").to_string(),
+            reason,
+        }
+    }
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {:?} at \"{}\"",
+            self.line, self.column, self.reason, self.token
+        )
+    }
+}
+
 pub struct Record {
     pub ussr_agent: ScriptedAgent,
     pub us_agent: ScriptedAgent,
@@ -58,15 +120,15 @@ impl Record {
             "US Place 4-WGermany 3-Italy\n",
             "Place 1-Italy 1-Iran"
         );
-        parse_lines(game_str)
+        parse_lines(game_str).expect("Valid standard start")
     }
 }
 
-impl Into<Replay> for Record {
-    fn into(self) -> Replay {
+impl From<Record> for Replay {
+    fn from(record: Record) -> Replay {
         let state = GameState::new();
-        let game = Game::new(state, self.rng);
-        Replay::new(self.us_agent, self.ussr_agent, game, self.triggers)
+        let game = Game::new(state, record.rng, RuleSet::default());
+        Replay::new(record.us_agent, record.ussr_agent, game, record.triggers)
     }
 }
 
@@ -86,6 +148,14 @@ fn space(x: &str) -> IResult<&str, &str> {
     nom::bytes::complete::is_a(" \t")(x)
 }
 
+fn skip_space(x: &str) -> &str {
+    space(x).map(|(left, _)| left).unwrap_or(x)
+}
+
+fn next_word(x: &str) -> Option<&str> {
+    x.split_whitespace().next()
+}
+
 fn find_single_card(s: &str) -> Option<Card> {
     use radix_trie::TrieCommon;
     let key = s.to_ascii_uppercase();
@@ -100,9 +170,39 @@ fn find_single_card(s: &str) -> Option<Card> {
     }
 }
 
+/// Finds a card by its full name or any unique prefix, ignoring case.
+pub fn find_card(s: &str) -> Option<Card> {
+    let key = s.to_ascii_uppercase();
+    CARDS.get(&key).copied().or_else(|| find_single_card(s))
+}
+
+/// Finds a country by its full name or any unique prefix, ignoring case.
+pub(crate) fn find_country(s: &str) -> Option<CName> {
+    if let Some(c) = COUNTRIES.get(s) {
+        return Some(*c);
+    }
+    let key = s.to_ascii_uppercase();
+    let mut matches = COUNTRIES
+        .iter()
+        .filter(|(k, _)| k.to_ascii_uppercase().starts_with(&key));
+    match (matches.next(), matches.next()) {
+        (Some((_, c)), None) => Some(*c),
+        _ => None,
+    }
+}
+
+/// Explains why `word` does not name a single card.
+fn card_error(word: &str) -> RecordErrorKind {
+    use radix_trie::TrieCommon;
+    match CARDS.get_raw_descendant(&word.to_ascii_uppercase()) {
+        Some(subtree) if subtree.len() > 1 => RecordErrorKind::AmbiguousCard,
+        _ => RecordErrorKind::UnknownCard,
+    }
+}
+
 fn card(x: &str) -> IResult<&str, Card> {
     let (left, word) = nom::bytes::complete::is_not(" \t")(x)?;
-    if let Some(card) = find_single_card(word) {
+    if let Some(card) = find_card(word) {
         Ok((left, card)) // Valid card
     } else {
         become_err![x]
@@ -115,15 +215,18 @@ enum MetaAction {
     Check,
     Touch,
     Roll,
+    /// A card picked at random from the side's hand, or none if it is empty.
+    Random,
+    /// Draw order after a reshuffle, first drawn first.
+    Reshuffle(Vec<Card>),
     Unknown,
 }
 
-fn action(x: &str) -> IResult<&str, MetaAction> {
-    let (left, word) = nom::bytes::complete::is_not(" \t")(x)?;
-    // Todo Realignment
+fn keyword(word: &str) -> Option<MetaAction> {
     let meta = match word {
         "Inf" => MetaAction::Real(Action::Influence),
         "Coup" => MetaAction::Real(Action::Coup),
+        "Realign" => MetaAction::Real(Action::Realignment),
         "Place" => MetaAction::Real(Action::Place),
         "Special" => MetaAction::Real(Action::SpecialEvent),
         "Remove" => MetaAction::Real(Action::Remove),
@@ -137,15 +240,30 @@ fn action(x: &str) -> IResult<&str, MetaAction> {
         "Discard" => MetaAction::Real(Action::Discard),
         "Space" => MetaAction::Real(Action::Space),
         "Pass" => MetaAction::Real(Action::Pass),
+        "Cuban" => MetaAction::Real(Action::CubanMissile),
+        "Recover" => MetaAction::Real(Action::RecoverCard),
+        "Defcon" => MetaAction::Real(Action::ChangeDefcon),
+        "Block" => MetaAction::Real(Action::BlockRegion),
+        "Double" => MetaAction::Real(Action::DoubleInf),
+        "Random" => MetaAction::Random,
         "Touch" => MetaAction::Touch,
         "Check" => MetaAction::Check,
-        _ => panic!("Unexpected keyword {}", word),
+        "Reshuffle" => MetaAction::Reshuffle(Vec::new()),
+        _ => return None,
     };
-    Ok((left, meta))
+    Some(meta)
+}
+
+fn action(x: &str) -> IResult<&str, MetaAction> {
+    let (left, word) = nom::bytes::complete::is_not(" \t")(x)?;
+    match keyword(word) {
+        Some(meta) => Ok((left, meta)),
+        None => become_err![x],
+    }
 }
 
 fn choices(x: &str) -> IResult<&str, Vec<usize>> {
-    let (left, list) = nom::multi::separated_list(tag(" "), is_not(" "))(x)?;
+    let (left, list) = nom::multi::separated_list(space, is_not(" \t"))(x)?;
     let mut output = Vec::new();
     for s in list {
         if let Ok(num) = s.parse::<usize>() {
@@ -160,7 +278,7 @@ fn choices(x: &str) -> IResult<&str, Vec<usize>> {
                 if let Ok(ex) = expanded {
                     output.extend(ex.1.into_iter());
                 } else {
-                    return become_err![x];
+                    return become_err![s];
                 }
             }
         }
@@ -190,20 +308,43 @@ struct Parsed {
     choices: Option<Vec<usize>>,
 }
 
-fn parse_line(line: &str, last_side: Side) -> Option<Parsed> {
-    let (_, (side, _, card, _, act, _, choices)) = tuple((
-        opt(side),
-        opt(space),
-        opt(card),
-        opt(space),
-        opt(action),
-        opt(space),
-        opt(choices),
-    ))(line)
-    .ok()?;
+fn parse_line(line: &str, last_side: Side) -> Result<Parsed, (&str, RecordErrorKind)> {
+    let (rest, side) = opt(side)(line).unwrap_or((line, None));
+    let rest = skip_space(rest);
+    // Anything before the action keyword must name a card
+    let (rest, card) = match next_word(rest) {
+        Some(word) if keyword(word).is_none() => match card(rest) {
+            Ok((left, c)) => (left, Some(c)),
+            Err(_) => return Err((rest, card_error(word))),
+        },
+        _ => (rest, None),
+    };
+    let rest = skip_space(rest);
+    let (rest, mut act) = if rest.is_empty() {
+        (rest, MetaAction::Unknown)
+    } else {
+        action(rest).map_err(|_| (rest, RecordErrorKind::UnknownKeyword))?
+    };
+    let rest = skip_space(rest);
+    let choices = if let MetaAction::Reshuffle(ref mut order) = act {
+        // Cards rather than choices follow the keyword
+        for word in rest.split_whitespace() {
+            match find_card(word) {
+                Some(c) => order.push(c),
+                None => return Err((word, card_error(word))),
+            }
+        }
+        None
+    } else if rest.is_empty() {
+        None
+    } else {
+        match choices(rest) {
+            Ok((_, c)) => Some(c),
+            Err(Error((at, _))) => return Err((at, RecordErrorKind::UnknownCountry)),
+            Err(_) => return Err((rest, RecordErrorKind::UnknownCountry)),
+        }
+    };
     let side = side.unwrap_or(last_side);
-    let act = act.unwrap_or(MetaAction::Unknown);
-    // dbg!(line);
     let act = if let MetaAction::Unknown = act {
         if let Some(c) = card {
             // Opponent card
@@ -213,12 +354,12 @@ fn parse_line(line: &str, last_side: Side) -> Option<Parsed> {
                 MetaAction::Real(Action::Ops)
             }
         } else {
-            unimplemented!()
+            return Err((rest, RecordErrorKind::MissingAction));
         }
     } else {
         act
     };
-    Some(Parsed {
+    Ok(Parsed {
         side,
         card,
         action: act,
@@ -226,81 +367,141 @@ fn parse_line(line: &str, last_side: Side) -> Option<Parsed> {
     })
 }
 
-pub fn parse_lines(string: &str) -> Record {
+/// Returns the first word of the line naming the card, or else the line.
+fn card_token(line: &str, card: Card) -> &str {
+    line.split_whitespace()
+        .find(|w| find_card(w) == Some(card))
+        .unwrap_or(line)
+}
+
+/// Parses a record into scripted agents and a debug RNG, or returns the
+/// first malformed line.
+pub fn parse_lines(string: &str) -> Result<Record, RecordError> {
     let mut last_side = Side::USSR;
     let mut us_rolls = Vec::new();
     let mut ussr_rolls = Vec::new();
     let mut us_cards = Vec::new();
     let mut ussr_cards = Vec::new();
+    let mut discards = Vec::new();
+    // Cards touched by each side and not played since, which were drawn
+    // already when they are played
+    let mut touched = [Vec::new(), Vec::new()];
     let mut choices = [Vec::new(), Vec::new()];
     let mut triggers = Vec::new();
-    for line in string.lines() {
-        if line.starts_with("#") {
+    let mut reshuffles = Vec::new();
+    // Where each card drawn or reshuffled is named, for replay errors
+    let mut us_at = Vec::new();
+    let mut ussr_at = Vec::new();
+    let mut reshuffles_at = Vec::new();
+    for (i, line) in string.lines().enumerate() {
+        if line.starts_with("#") || line.trim().is_empty() {
             continue;
         }
-        if let Some(parsed) = parse_line(line, last_side) {
-            last_side = parsed.side;
-            match parsed.action {
-                MetaAction::Roll => {
-                    let roll = parsed.choices.unwrap()[0] as i8;
-                    match parsed.side {
-                        Side::US => us_rolls.push(roll),
-                        Side::USSR => ussr_rolls.push(roll),
-                        _ => unimplemented!(),
-                    }
+        let error = |at: &str, reason| RecordError::new(i + 1, line, at, reason);
+        let end = &line[line.len()..];
+        let named = |card| error(card_token(line, card), RecordErrorKind::NotInDrawPile);
+        let parsed = parse_line(line, last_side).map_err(|(at, reason)| error(at, reason))?;
+        last_side = parsed.side;
+        match parsed.action {
+            MetaAction::Roll => {
+                let roll = match parsed.choices.as_ref().and_then(|c| c.first()) {
+                    Some(&roll) => roll as i8,
+                    None => return Err(error(end, RecordErrorKind::MissingChoices)),
+                };
+                match parsed.side {
+                    Side::US => us_rolls.push(roll),
+                    Side::USSR => ussr_rolls.push(roll),
+                    _ => unimplemented!(),
                 }
-                MetaAction::Touch => {
-                    let card = parsed.card.expect("Found a card to add");
-                    match parsed.side {
-                        Side::US => us_cards.push(card),
-                        Side::USSR => ussr_cards.push(card),
-                        _ => unimplemented!(),
+            }
+            MetaAction::Touch => {
+                let card = parsed
+                    .card
+                    .ok_or_else(|| error(line, RecordErrorKind::MissingCard))?;
+                match parsed.side {
+                    Side::US => {
+                        us_cards.push(card);
+                        us_at.push(named(card));
                     }
+                    Side::USSR => {
+                        ussr_cards.push(card);
+                        ussr_at.push(named(card));
+                    }
+                    _ => unimplemented!(),
                 }
-                MetaAction::Check => {
-                    let count = choices[0].len() + choices[1].len();
-                    triggers.push(count);
-                }
-                MetaAction::Real(act) => match act {
-                    Action::Event
-                    | Action::EventOps
-                    | Action::Ops
-                    | Action::OpsEvent
-                    | Action::ChooseCard
-                    | Action::Discard
-                    | Action::Space => {
-                        let card = parsed.card.unwrap();
-                        if card != Card::The_China_Card {
-                            // Todo find exceptions to this
-                            match parsed.side {
-                                Side::US => us_cards.push(card),
-                                Side::USSR => ussr_cards.push(card),
-                                _ => unimplemented!(),
+                touched[parsed.side as usize].push(card);
+            }
+            MetaAction::Random => discards.push(parsed.card),
+            MetaAction::Reshuffle(order) => {
+                reshuffles_at.push(order.iter().map(|&c| named(c)).collect());
+                reshuffles.push(order);
+            }
+            MetaAction::Check => {
+                let count = choices[0].len() + choices[1].len();
+                triggers.push(count);
+            }
+            MetaAction::Real(act) => match act {
+                Action::Event
+                | Action::EventOps
+                | Action::Ops
+                | Action::OpsEvent
+                | Action::ChooseCard
+                | Action::Discard
+                | Action::Space => {
+                    let card = parsed
+                        .card
+                        .ok_or_else(|| error(line, RecordErrorKind::MissingCard))?;
+                    let hand = &mut touched[parsed.side as usize];
+                    if let Some(i) = hand.iter().position(|&c| c == card) {
+                        hand.swap_remove(i);
+                    } else if card != Card::The_China_Card {
+                        // Todo find exceptions to this
+                        match parsed.side {
+                            Side::US => {
+                                us_cards.push(card);
+                                us_at.push(named(card));
                             }
+                            Side::USSR => {
+                                ussr_cards.push(card);
+                                ussr_at.push(named(card));
+                            }
+                            _ => unimplemented!(),
                         }
-                        let x = OutputIndex::encode_single(act, card as usize);
-                        choices[parsed.side as usize].push(x);
                     }
-                    Action::Influence
-                    | Action::Coup
-                    | Action::Realignment
-                    | Action::Place
-                    | Action::Remove
-                    | Action::War
-                    | Action::SpecialEvent => {
-                        for choice in parsed.choices.unwrap() {
-                            let x = OutputIndex::encode_single(act, choice);
-                            choices[parsed.side as usize].push(x);
-                        }
+                    let x = OutputIndex::encode_single(act, card as usize);
+                    choices[parsed.side as usize].push(x);
+                }
+                Action::RecoverCard => {
+                    // The card comes from the discard pile, not a draw
+                    let card = parsed
+                        .card
+                        .ok_or_else(|| error(line, RecordErrorKind::MissingCard))?;
+                    let x = OutputIndex::encode_single(act, card as usize);
+                    choices[parsed.side as usize].push(x);
+                }
+                Action::Influence
+                | Action::Coup
+                | Action::Realignment
+                | Action::Place
+                | Action::Remove
+                | Action::War
+                | Action::SpecialEvent
+                | Action::CubanMissile
+                | Action::ChangeDefcon
+                | Action::BlockRegion
+                | Action::DoubleInf => {
+                    let list = parsed
+                        .choices
+                        .ok_or_else(|| error(end, RecordErrorKind::MissingChoices))?;
+                    for choice in list {
+                        let x = OutputIndex::encode_single(act, choice);
+                        choices[parsed.side as usize].push(x);
                     }
-                    Action::Pass => choices[parsed.side as usize].push(OutputIndex::pass()),
-                    _ => unimplemented!(),
-                },
-                _ => unimplemented!(),
-            }
-        } else {
-            dbg!(line);
-            unimplemented!();
+                }
+                Action::Pass => choices[parsed.side as usize].push(OutputIndex::pass()),
+                _ => unreachable!("Not produced by any keyword"),
+            },
+            MetaAction::Unknown => unreachable!("Resolved by parse_line"),
         }
     }
     // Reverse rolls since we remove them LIFO instead of FIFO
@@ -309,16 +510,24 @@ pub fn parse_lines(string: &str) -> Record {
     triggers.reverse();
     us_cards.reverse();
     ussr_cards.reverse();
+    discards.reverse();
     let us_agent = ScriptedAgent::new(&choices[Side::US as usize]);
     let ussr_agent = ScriptedAgent::new(&choices[Side::USSR as usize]);
-    // dbg!(&us_agent.choices.lock().unwrap());
-    let rng = DebugRand::new(us_rolls, ussr_rolls, vec![], us_cards, ussr_cards);
-    Record {
+    reshuffles.reverse();
+    us_at.reverse();
+    ussr_at.reverse();
+    reshuffles_at.reverse();
+    let mut rng = DebugRand::new(us_rolls, ussr_rolls, discards, us_cards, ussr_cards);
+    rng.reshuffles = reshuffles;
+    rng.us_draw_at = us_at;
+    rng.ussr_draw_at = ussr_at;
+    rng.reshuffles_at = reshuffles_at;
+    Ok(Record {
         ussr_agent,
         us_agent,
         rng,
         triggers,
-    }
+    })
 }
 
 #[cfg(test)]
@@ -361,6 +570,44 @@ mod tests {
         dbg!(act);
     }
 
+    #[test]
+    fn parse_errors() {
+        let err = |s: &str| parse_lines(s).err().unwrap();
+        let e = err("USSR Place 4-Poland\nUS Place 4-WGermany 3-Atlantis");
+        assert_eq!((e.line, e.column), (2, 21));
+        assert_eq!(e.token, "3-Atlantis");
+        assert_eq!(e.reason, RecordErrorKind::UnknownCountry);
+        let e = err("# Comment\nUS Latin_American O");
+        assert_eq!((e.line, e.column), (2, 4));
+        assert_eq!(e.reason, RecordErrorKind::AmbiguousCard);
+        assert_eq!(err("US Nonsense O").reason, RecordErrorKind::UnknownCard);
+        let e = err("US NATO Fly");
+        assert_eq!((e.column, e.token.as_str()), (9, "Fly"));
+        assert_eq!(e.reason, RecordErrorKind::UnknownKeyword);
+        let e = err("USSR Roll");
+        assert_eq!((e.column, e.token.as_str()), (10, ""));
+        assert_eq!(e.reason, RecordErrorKind::MissingChoices);
+        assert_eq!(err("USSR Place").reason, RecordErrorKind::MissingChoices);
+        assert_eq!(err("US E").reason, RecordErrorKind::MissingCard);
+        assert_eq!(err("US").reason, RecordErrorKind::MissingAction);
+        let e = err("Reshuffle NATO Atlantis");
+        assert_eq!((e.column, e.reason), (16, RecordErrorKind::UnknownCard));
+    }
+
+    #[test]
+    fn parse_reshuffle() {
+        let record = parse_lines("Reshuffle\nReshuffle Che NATO Duck").unwrap();
+        let order = vec![Card::Che, Card::NATO, Card::Duck_and_Cover];
+        assert_eq!(record.rng.reshuffles, vec![order, vec![]]);
+    }
+
+    #[test]
+    fn parse_touch() {
+        // A touched card played later was only drawn once
+        let record = parse_lines("US NATO Touch\nUS Che Touch\nUS NATO E\nUS NATO E").unwrap();
+        assert_eq!(record.rng.us_draw, vec![Card::NATO, Card::Che, Card::NATO]);
+    }
+
     #[test]
     fn radix_test() {
         assert!(find_single_card("Latin_American").is_none());
@@ -0,0 +1,366 @@
+use crate::action::Action;
+use crate::card::Card;
+use crate::country::{CName, Side};
+use crate::record::{RecordError, RecordErrorKind};
+use crate::state::{GameState, RandEvent};
+use crate::tensor::DecodedChoice;
+
+/// Writes a game as it is played in the same line format read by
+/// `parse_lines`, so that it can be replayed later.
+///
+/// Choices must be passed to `choice` before they are consumed, and the
+/// results of the RNG passed to `rand_event`, e.g. from a `RecordingRand`,
+/// as they happen. Cards drawn but not yet played are written as `Touch`
+/// lines just before the next card that side plays, which keeps the draw
+/// order intact. Choices of trivial decisions are left out, since replays
+/// make them automatically.
+#[derive(Default)]
+pub struct RecordWriter {
+    lines: Vec<String>,
+    turn: Option<i8>,
+    ar: Option<i8>,
+    /// Consecutive country or number choices of one side, merged onto a line.
+    open: Option<(Side, Action, Vec<usize>)>,
+    /// Cards drawn by each side that have not been written yet.
+    drawn: [Vec<Card>; 2],
+}
+
+impl RecordWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Writes the choice about to be consumed in the given state, or returns
+    /// an error if the record format cannot express it.
+    pub fn choice(&mut self, state: &GameState, choice: &DecodedChoice) -> Result<(), RecordError> {
+        let decision = match state.peek_pending() {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        if decision.is_trivial() {
+            // Still mention the card so that it keeps its place in the draws
+            if let (true, Some(c)) = (is_card_action(choice.action), choice.choice) {
+                self.close();
+                self.touch(decision.agent, Card::from_index(c), true);
+            }
+            return Ok(());
+        }
+        let act = choice.action;
+        if keyword(act).is_none() {
+            return Err(RecordError {
+                line: 0,
+                column: 0,
+                token: format!("{:?}", choice),
+                reason: RecordErrorKind::UnsupportedChoice,
+            });
+        }
+        let side = decision.agent;
+        if self.turn != Some(state.turn) {
+            self.close();
+            self.lines.push(format!("#T{}", state.turn));
+            self.turn = Some(state.turn);
+            self.ar = None;
+        }
+        if self.ar != Some(state.ar) {
+            self.close();
+            if state.ar == 0 {
+                self.lines.push("#HL".to_string());
+            } else {
+                self.lines.push(format!("#AR{}", state.ar));
+            }
+            self.ar = Some(state.ar);
+        }
+        match (keyword(act), choice.choice) {
+            (Some(word), Some(c)) if is_card_action(act) => {
+                let card = Card::from_index(c);
+                self.close();
+                self.touch(side, card, false);
+                self.lines.push(format!("{:?} {:?} {}", side, card, word));
+            }
+            (Some(word), Some(c)) if act == Action::RecoverCard => {
+                // Recovered from the discard pile, so not one of the draws
+                self.close();
+                let card = Card::from_index(c);
+                self.lines.push(format!("{:?} {:?} {}", side, card, word));
+            }
+            (Some(_), Some(c)) => match &mut self.open {
+                Some((s, a, vec)) if *s == side && *a == act => vec.push(c),
+                _ => {
+                    self.close();
+                    self.open = Some((side, act, vec![c]));
+                }
+            },
+            (Some(word), None) => {
+                self.close();
+                self.lines.push(format!("{:?} {}", side, word));
+            }
+            (None, _) => unreachable!("Checked above"),
+        }
+        Ok(())
+    }
+    /// Writes a random outcome logged by a `RecordingRand`.
+    pub fn rand_event(&mut self, event: &RandEvent) {
+        match *event {
+            RandEvent::Roll(side, x) => self.roll(side, x),
+            RandEvent::CardFromHand(side, c) => self.random_card(side, c),
+            RandEvent::Draw(side, c) => self.draw(side, c),
+            RandEvent::Reshuffle(ref pile) => {
+                self.close();
+                let mut line = "Reshuffle".to_string();
+                // The pile is stored bottom first, but written in draw order
+                for c in pile.iter().rev() {
+                    line.push_str(&format!(" {:?}", c));
+                }
+                self.lines.push(line);
+            }
+        }
+    }
+    /// Writes a die roll made for the given side.
+    pub fn roll(&mut self, side: Side, roll: i8) {
+        self.close();
+        self.lines.push(format!("{:?} Roll {}", side, roll));
+    }
+    /// Notes a card drawn by the given side.
+    pub fn draw(&mut self, side: Side, card: Card) {
+        if card != Card::Dummy {
+            self.drawn[side as usize].push(card);
+        }
+    }
+    /// Writes a card picked at random from a hand, or `None` if the hand was
+    /// empty. The card is touched first, as it was drawn earlier.
+    pub fn random_card(&mut self, side: Side, card: Option<Card>) {
+        self.close();
+        match card {
+            Some(card) => {
+                self.touch(side, card, true);
+                self.lines.push(format!("{:?} {:?} Random", side, card));
+            }
+            None => self.lines.push(format!("{:?} Random", side)),
+        }
+    }
+    /// Writes a comment line.
+    pub fn comment(&mut self, text: &str) {
+        self.close();
+        self.lines.push(format!("#{}", text));
+    }
+    /// Returns the finished record, touching any cards still in hand.
+    pub fn finish(mut self) -> String {
+        self.close();
+        for &side in [Side::US, Side::USSR].iter() {
+            for card in std::mem::take(&mut self.drawn[side as usize]) {
+                self.lines.push(format!("{:?} {:?} Touch", side, card));
+            }
+        }
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+    /// Writes the cards the side drew before the given card as `Touch` lines,
+    /// including the card itself if `inclusive`.
+    fn touch(&mut self, side: Side, card: Card, inclusive: bool) {
+        let drawn = &mut self.drawn[side as usize];
+        if let Some(i) = drawn.iter().position(|&c| c == card) {
+            let earlier: Vec<_> = drawn.drain(..=i).collect();
+            let end = if inclusive { i + 1 } else { i };
+            for c in earlier[..end].iter() {
+                self.lines.push(format!("{:?} {:?} Touch", side, c));
+            }
+        }
+    }
+    /// Writes out the line of merged choices, if any.
+    fn close(&mut self) {
+        let (side, act, vec) = match self.open.take() {
+            Some(x) => x,
+            None => return,
+        };
+        let mut line = format!("{:?} {}", side, keyword(act).unwrap());
+        if is_numeric(act) {
+            // Runs can only be shortened for countries
+            for x in vec.iter() {
+                line.push_str(&format!(" {}", x));
+            }
+            self.lines.push(line);
+            return;
+        }
+        let mut i = 0;
+        while i < vec.len() {
+            let run = vec[i..].iter().take_while(|&&x| x == vec[i]).count();
+            let choice = format!("{:?}", CName::from_index(vec[i]));
+            if run > 1 {
+                line.push_str(&format!(" {}-{}", run, choice));
+            } else {
+                line.push_str(&format!(" {}", choice));
+            }
+            i += run;
+        }
+        self.lines.push(line);
+    }
+}
+
+fn is_card_action(action: Action) -> bool {
+    use Action::*;
+    match action {
+        Event | EventOps | Ops | OpsEvent | ChooseCard | Discard | Space => true,
+        _ => false,
+    }
+}
+
+/// Whether the action's choices are written as numbers rather than countries.
+fn is_numeric(action: Action) -> bool {
+    use Action::*;
+    matches!(
+        action,
+        SpecialEvent | CubanMissile | ChangeDefcon | BlockRegion | DoubleInf
+    )
+}
+
+/// Returns the record keyword for the action, the inverse of `keyword` in
+/// the parser.
+fn keyword(action: Action) -> Option<&'static str> {
+    let word = match action {
+        Action::Influence => "Inf",
+        Action::Coup => "Coup",
+        Action::Realignment => "Realign",
+        Action::Place => "Place",
+        Action::SpecialEvent => "Special",
+        Action::Remove => "Remove",
+        Action::ChooseCard => "HL",
+        Action::OpsEvent => "OE",
+        Action::EventOps => "EO",
+        Action::Event => "E",
+        Action::Ops => "O",
+        Action::War => "War",
+        Action::Discard => "Discard",
+        Action::Space => "Space",
+        Action::Pass => "Pass",
+        Action::CubanMissile => "Cuban",
+        Action::RecoverCard => "Recover",
+        Action::ChangeDefcon => "Defcon",
+        Action::BlockRegion => "Block",
+        Action::DoubleInf => "Double",
+        _ => return None,
+    };
+    Some(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ScriptedAgent;
+    use crate::game::replay::Replay;
+    use crate::game::{Game, RuleSet, Start};
+    use crate::record::parse_lines;
+    use crate::state::DebugRand;
+    use crate::tensor::{OutputIndex, TensorOutput};
+
+    /// Notes the draws and rolls taken from `rng` since `before` was cloned.
+    fn log_rng(writer: &mut RecordWriter, before: &DebugRand, rng: &DebugRand) {
+        let sides = [
+            (Side::US, &before.us_rolls, &rng.us_rolls),
+            (Side::USSR, &before.ussr_rolls, &rng.ussr_rolls),
+        ];
+        for &(side, old, new) in sides.iter() {
+            for &roll in old[new.len()..].iter().rev() {
+                writer.roll(side, roll);
+            }
+        }
+        let sides = [
+            (Side::US, &before.us_draw, &rng.us_draw),
+            (Side::USSR, &before.ussr_draw, &rng.ussr_draw),
+        ];
+        for &(side, old, new) in sides.iter() {
+            for &card in old[new.len()..].iter().rev() {
+                writer.draw(side, card);
+            }
+        }
+    }
+
+    /// Plays a record the way `Replay` does while writing it back out.
+    fn rewrite(text: &str) -> (String, GameState) {
+        let record = parse_lines(text).unwrap();
+        let agents = [record.us_agent, record.ussr_agent];
+        let mut game = Game::new(GameState::new(), record.rng, RuleSet::default());
+        let mut writer = RecordWriter::new();
+        let before = game.rng.clone();
+        game.setup(Start::Beginning);
+        log_rng(&mut writer, &before, &game.rng);
+        let remaining = |a: &ScriptedAgent| a.choices.lock().unwrap().len();
+        while agents.iter().any(|a| remaining(a) > 0) {
+            let next = game.state.peek_pending().unwrap().clone();
+            let agent = &agents[next.agent as usize];
+            let decoded = if next.is_trivial() {
+                let mut x = next.clone();
+                let action = x.encode(&game.state).first().copied();
+                agent.trivial_action(action);
+                action.unwrap_or(OutputIndex::pass()).decode()
+            } else {
+                agent.choices.lock().unwrap().pop().unwrap().decode()
+            };
+            writer.choice(&game.state, &decoded).unwrap();
+            let before = game.rng.clone();
+            game.consume_action(decoded).unwrap();
+            log_rng(&mut writer, &before, &game.rng);
+        }
+        (writer.finish(), game.state)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = std::fs::read_to_string("tests/Events1.record").unwrap();
+        let (written, original) = rewrite(&text);
+        assert!(written.starts_with("#T0\n#HL\nUSSR Place 4-Poland EGermany Austria\n"));
+        assert!(written.contains("\n#T1\n"));
+        let mut replay: Replay = parse_lines(&written).unwrap().into();
+        assert!(replay.play(Start::Beginning).unwrap().is_none());
+        let state = &replay.game.state;
+        let inf = |s: &GameState| {
+            let c = &s.countries;
+            c.iter().map(|x| (x.us, x.ussr)).collect::<Vec<_>>()
+        };
+        assert_eq!(inf(state), inf(&original));
+        assert_eq!((state.turn, state.ar), (original.turn, original.ar));
+        assert_eq!(state.vp, original.vp);
+        assert_eq!(state.deck, original.deck);
+    }
+
+    #[test]
+    fn test_touch_order() {
+        let state = GameState::new();
+        let mut writer = RecordWriter::new();
+        writer.draw(Side::US, Card::NATO);
+        writer.draw(Side::US, Card::Che);
+        writer.draw(Side::US, Card::Containment);
+        writer.draw(Side::US, Card::Dummy);
+        writer.roll(Side::US, 3);
+        writer.comment("Note");
+        // Without a pending decision nothing is written
+        let choice = DecodedChoice::new(Action::Event, Some(1));
+        assert!(writer.choice(&state, &choice).is_ok());
+        let text = writer.finish();
+        assert_eq!(
+            text,
+            "US Roll 3\n#Note\nUS NATO Touch\nUS Che Touch\nUS Containment Touch\n"
+        );
+        let record = parse_lines(&text).unwrap();
+        let draws = [Card::Containment, Card::Che, Card::NATO];
+        assert_eq!(record.rng.us_draw, draws);
+    }
+
+    #[test]
+    fn test_random_card() {
+        let mut writer = RecordWriter::new();
+        writer.draw(Side::US, Card::NATO);
+        writer.draw(Side::US, Card::Che);
+        writer.draw(Side::US, Card::Containment);
+        writer.rand_event(&RandEvent::CardFromHand(Side::US, Some(Card::Che)));
+        writer.rand_event(&RandEvent::CardFromHand(Side::USSR, None));
+        let text = writer.finish();
+        assert_eq!(
+            text,
+            "US NATO Touch\nUS Che Touch\nUS Che Random\nUSSR Random\nUS Containment Touch\n"
+        );
+        let record = parse_lines(&text).unwrap();
+        let draws = [Card::Containment, Card::Che, Card::NATO];
+        assert_eq!(record.rng.us_draw, draws);
+        assert_eq!(record.rng.discards, [None, Some(Card::Che)]);
+    }
+}
@@ -1,13 +1,28 @@
-use crate::action::{Action, Allowed, Decision, Restriction};
+use crate::action::{Action, Allowed, Decision, LazyAllowed, Restriction};
 use crate::card::*;
 use crate::country::*;
+use crate::game::{EndReason, RuleSet};
 use crate::tensor::{DecodedChoice, OutputIndex, TensorOutput};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+mod board;
+mod delta;
+mod event;
+mod notation;
 mod random;
-pub use random::{DebugRand, InternalRand, TwilightRand};
+mod validate;
+mod view;
+pub use delta::Delta;
+pub use event::GameEvent;
+pub use notation::NotationError;
+pub use random::{DebugRand, InternalRand, RandEvent, RecordingRand, TwilightRand};
+pub use validate::InvariantError;
+pub use view::PlayerView;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GameState {
     pub countries: Vec<Country>,
     pub vp: i8,
@@ -27,7 +42,13 @@ pub struct GameState {
     pub china: bool,
     pub iron_lady: bool,
     pub chernobyl: Option<Region>,
+    end_reason: Option<EndReason>,
     pending: Vec<Decision>,
+    rules: RuleSet,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    events: Option<Vec<GameEvent>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    journal: Option<Vec<Delta>>,
 }
 
 impl GameState {
@@ -51,9 +72,45 @@ impl GameState {
             china: false,
             iron_lady: false,
             chernobyl: None,
+            end_reason: None,
             pending: Vec::new(),
+            rules: RuleSet::default(),
+            events: None,
+            journal: None,
         }
     }
+    /// Returns why the VP track was set to end the game, if an event or a
+    /// scoring did so directly.
+    pub fn end_reason(&self) -> Option<EndReason> {
+        self.end_reason
+    }
+    pub fn set_end_reason(&mut self, reason: EndReason) {
+        let old = self.end_reason.replace(reason);
+        self.record(Delta::EndReason(old));
+    }
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+    /// Sets the rules the game is played with, updating the deck to match.
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        assert!(rules.is_valid(), "Invalid rules: {:?}", rules);
+        if !rules.optional_cards {
+            self.deck.remove_optional_cards();
+        }
+        self.rules = rules;
+    }
+    /// Adds the Mid War cards to the draw pile, as the rules allow.
+    pub fn add_mid_war(&mut self) {
+        self.deck.add_mid_war(self.rules.optional_cards);
+    }
+    /// Adds the Late War cards to the draw pile, as the rules allow.
+    pub fn add_late_war(&mut self) {
+        self.deck.add_late_war(self.rules.optional_cards);
+    }
+    /// Returns every card that has entered the deck by the current period.
+    pub fn cards_in_play(&self) -> Vec<Card> {
+        Deck::cards_in_play(self.period(), self.rules.optional_cards)
+    }
     pub fn four_four_two() -> GameState {
         use crate::country::CName::*;
         let mut state = GameState::new();
@@ -67,15 +124,16 @@ impl GameState {
         state
     }
     pub fn advance_ply(&mut self) -> Result<(), Win> {
+        self.record_flags();
         self.china = false; // Todo ensure China flag doesn't get left on
         self.iron_lady = false;
         self.chernobyl = None;
         self.check_win()?;
         if let Side::US = self.side {
-            self.ar += 1;
+            self.set_ar(self.ar + 1);
         }
-        self.side = self.side.opposite();
-        self.deck.flush_pending();
+        self.set_side(self.side.opposite());
+        self.flush_pending();
         Ok(())
     }
     pub fn advance_turn(&mut self) -> Result<(), Win> {
@@ -95,21 +153,23 @@ impl GameState {
         let us_pen = max(defcon - self.mil_ops[Side::US as usize], 0);
         let ussr_pen = max(defcon - self.mil_ops[Side::USSR as usize], 0);
         // These are penalties, so the signs are reversed from usual
-        self.vp -= us_pen;
-        self.vp += ussr_pen;
-        self.turn += 1;
-        self.ar = 0;
+        self.add_vp(ussr_pen - us_pen);
+        self.set_turn(self.turn + 1);
+        self.set_ar(0);
         // Reset Defcon and Mil ops for next turn
         self.set_defcon(defcon + 1);
-        self.mil_ops[0] = 0;
-        self.mil_ops[1] = 0;
+        self.set_mil_ops(Side::US, 0);
+        self.set_mil_ops(Side::USSR, 0);
         // Check win before cleanup due to scoring cards held
         self.check_win()?;
-        self.deck.end_turn_cleanup();
+        self.set_china(self.deck.china(), true);
         self.turn_effect_clear();
         Ok(())
     }
     pub fn check_win(&self) -> Result<(), Win> {
+        if let Some(EndReason::Wargames) = self.end_reason {
+            return Err(Win::from_vp(self.vp));
+        }
         if self.defcon() < 2 {
             let side = self.side.opposite();
             return Err(Win::Defcon(side));
@@ -125,6 +185,8 @@ impl GameState {
         self.defcon
     }
     pub fn set_defcon(&mut self, value: i8) {
+        let from = self.defcon;
+        self.record(Delta::Defcon(from));
         if value > 5 {
             self.defcon = 5;
         } else if value < 1 {
@@ -137,16 +199,46 @@ impl GameState {
             {
                 // Add US decision at end of AR
                 assert!(self.pending[0].action == Action::EndAr);
-                let allowed = Allowed::new_lazy(crate::card::legal::norad);
+                let allowed = Allowed::new_lazy(LazyAllowed::Norad);
                 let d = Decision::new(Side::US, Action::Place, allowed);
                 self.pending.insert(1, d);
+                self.record(Delta::PendingInserted(1));
             }
             self.defcon = value;
         }
+        if self.defcon != from {
+            self.emit(GameEvent::Defcon {
+                from,
+                to: self.defcon,
+            });
+        }
     }
     pub fn side(&self) -> &Side {
         &self.side
     }
+    pub fn set_side(&mut self, side: Side) {
+        self.record(Delta::Side(self.side));
+        self.side = side;
+    }
+    pub fn set_turn(&mut self, turn: i8) {
+        self.record(Delta::Turn(self.turn));
+        self.turn = turn;
+    }
+    pub fn set_ar(&mut self, ar: i8) {
+        self.record(Delta::Ar(self.ar));
+        self.ar = ar;
+    }
+    pub fn add_vp(&mut self, change: i8) {
+        self.set_vp(self.vp + change);
+    }
+    pub fn set_vp(&mut self, vp: i8) {
+        let change = vp - self.vp;
+        self.record(Delta::Vp(self.vp));
+        self.vp = vp;
+        if change != 0 {
+            self.emit(GameEvent::Vp { change, total: vp });
+        }
+    }
     pub fn max_ar(&self, side: Side) -> i8 {
         if self.turn <= 3 {
             6
@@ -206,6 +298,25 @@ impl GameState {
             _ => unimplemented!(),
         }
     }
+    /// Picks a random card from the hand of the side, leaving out a headline
+    /// it has chosen but not yet played.
+    pub fn random_card<R: TwilightRand>(&mut self, side: Side, rng: &mut R) -> Option<Card> {
+        let headline = self
+            .pending
+            .iter()
+            .find(|d| self.ar == 0 && d.agent == side && d.action == Action::Event)
+            .map(|d| Card::from_index(d.allowed.try_slice().unwrap()[0]));
+        let hand = self.deck.hand_mut(side);
+        let hidden = headline.and_then(|c| {
+            let i = hand.iter().position(|&x| x == c)?;
+            Some((i, hand.remove(i)))
+        });
+        let card = self.deck.random_card(side, rng);
+        if let Some((i, c)) = hidden {
+            self.deck.hand_mut(side).insert(i, c);
+        }
+        card
+    }
     /// Handle discarding cards to deal with weird special cases like Grain Sales.
     pub fn discard_card(&mut self, side: Side, card: Card) {
         let side = if let Some(Card::Grain_Sales) = self.current_event() {
@@ -218,7 +329,88 @@ impl GameState {
         } else {
             side
         };
-        self.deck.play_card(side, card).expect("Found");
+        if card == Card::The_China_Card {
+            self.set_china(self.deck.china().opposite(), false);
+        } else if !self.deck.pending_discard().contains(&card) {
+            let hand = Pile::Hand(side);
+            self.move_card(card, hand, Pile::PendingDiscard)
+                .expect("Found");
+        }
+        if card == Card::Missile_Envy {
+            // However it was played, Missile Envy has left the hand
+            if let Some(i) = self.effect_pos(side, Effect::MissileEnvy) {
+                self.clear_effect(side, i);
+            }
+        }
+    }
+    /// Moves the card from one pile to the top of another.
+    pub fn move_card(&mut self, card: Card, from: Pile, to: Pile) -> Result<(), DeckError> {
+        let index = self.deck.move_card(card, from, to)?;
+        self.record(Delta::CardTaken(from, index, card));
+        self.record(Delta::CardPut(to));
+        match (from, to) {
+            (_, Pile::Hand(side)) => self.emit(GameEvent::CardDrawn { side, card }),
+            (Pile::PendingDiscard, Pile::Discard) => {}
+            (_, Pile::Discard) | (_, Pile::PendingDiscard) => {
+                self.emit(GameEvent::CardDiscarded { card })
+            }
+            (_, Pile::Removed) => self.emit(GameEvent::CardRemoved { card }),
+            (_, Pile::Draw) => {}
+        }
+        Ok(())
+    }
+    /// Returns the pile a played card is in: the pending discard pile, or
+    /// else the discard pile, where Star Wars plays events from.
+    fn played_pile(&self, card: Card) -> Pile {
+        if self.deck.pending_discard().contains(&card) {
+            Pile::PendingDiscard
+        } else {
+            Pile::Discard
+        }
+    }
+    /// Discards the cards played this action round, in the order played.
+    pub fn flush_pending(&mut self) {
+        while let Some(&card) = self.deck.pending_discard().first() {
+            self.move_card(card, Pile::PendingDiscard, Pile::Discard)
+                .unwrap();
+        }
+    }
+    pub fn set_china(&mut self, holder: Side, face_up: bool) {
+        let old = self.deck.china();
+        self.record(Delta::China {
+            holder: old,
+            face_up: self.deck.china_available(old),
+        });
+        self.deck.set_china(holder, face_up);
+    }
+    /// Deals cards until both hands hold the target number of cards.
+    pub fn draw_cards<R: TwilightRand>(&mut self, target: usize, rng: &mut R) {
+        self.record_piles();
+        let held = [self.deck.us_hand().len(), self.deck.ussr_hand().len()];
+        self.deck.draw_cards(target, rng);
+        for &side in [Side::US, Side::USSR].iter() {
+            for i in held[side as usize]..self.deck.hand(side).len() {
+                let card = self.deck.hand(side)[i];
+                self.record(Delta::CardPut(Pile::Hand(side)));
+                self.emit(GameEvent::CardDrawn { side, card });
+            }
+        }
+    }
+    /// Moves five cards from the draw pile to the end of it, returning them.
+    pub fn our_man<R: TwilightRand>(&mut self, rng: &mut R) -> &[Card] {
+        self.record_piles();
+        self.deck.our_man(rng)
+    }
+    /// Draws the number of cards into the side's hand.
+    pub fn draw_to_hand<R: TwilightRand>(&mut self, side: Side, count: usize, rng: &mut R) {
+        self.record_piles();
+        for _ in 0..count {
+            self.deck.draw_to_hand(rng, side);
+            self.record(Delta::CardPut(Pile::Hand(side)));
+            if let Some(&card) = self.deck.hand(side).last() {
+                self.emit(GameEvent::CardDrawn { side, card });
+            }
+        }
     }
     pub fn resolve_action<R: TwilightRand>(
         &mut self,
@@ -252,6 +444,11 @@ impl GameState {
         match decision.action {
             Action::EventOps => {
                 let card = Card::from_index(choice);
+                self.emit(GameEvent::CardPlayed {
+                    side,
+                    card,
+                    action: decision.action,
+                });
                 let ops = card.modified_ops(decision.agent, self);
                 let conduct = Decision::conduct_ops(decision.agent, ops);
                 let event = Decision::new_event(side, card);
@@ -261,28 +458,40 @@ impl GameState {
             }
             Action::OpsEvent => {
                 let card = Card::from_index(choice);
+                self.emit(GameEvent::CardPlayed {
+                    side,
+                    card,
+                    action: decision.action,
+                });
                 let ops = card.modified_ops(decision.agent, self);
                 let conduct = Decision::conduct_ops(decision.agent, ops);
                 let event = Decision::new_event(side, card);
                 self.add_pending(event);
                 self.add_pending(conduct);
                 if card == Card::The_China_Card {
+                    self.record_flags();
                     self.china = true;
                 }
                 self.discard_card(side, card);
             }
             Action::Ops => {
                 let card = Card::from_index(choice);
+                self.emit(GameEvent::CardPlayed {
+                    side,
+                    card,
+                    action: decision.action,
+                });
                 let mut ops = card.modified_ops(decision.agent, self);
                 if card == Card::The_China_Card {
                     ops += 1;
+                    self.record_flags();
                     self.china = true;
                 } else if card.is_war()
                     && decision.agent == Side::US
                     && card.can_event(self, side)
                     && self.has_effect(Side::USSR, Effect::FlowerPower)
                 {
-                    self.vp -= 2;
+                    self.add_vp(-2);
                 }
                 let conduct = Decision::conduct_ops(decision.agent, ops);
                 self.add_pending(conduct);
@@ -290,6 +499,11 @@ impl GameState {
             }
             Action::Event => {
                 let card = Card::from_index(choice);
+                self.emit(GameEvent::CardPlayed {
+                    side,
+                    card,
+                    action: decision.action,
+                });
                 // Do not discard for star wars, since it's already there
                 match self.current_event() {
                     Some(c) if c == Card::Star_Wars => {}
@@ -301,10 +515,12 @@ impl GameState {
                     && card.can_event(self, side)
                     && self.has_effect(Side::USSR, Effect::FlowerPower)
                 {
-                    self.vp -= 2;
+                    self.add_vp(-2);
                 }
                 if card.event(self, rng) && card.is_starred() {
-                    self.deck.remove_card(card).expect("Remove Failure");
+                    let pile = self.played_pile(card);
+                    self.move_card(card, pile, Pile::Removed)
+                        .expect("Remove Failure");
                 }
             }
             Action::SpecialEvent => {
@@ -314,6 +530,11 @@ impl GameState {
             }
             Action::Space => {
                 let card = Card::from_index(choice);
+                self.emit(GameEvent::CardPlayed {
+                    side,
+                    card,
+                    action: decision.action,
+                });
                 let roll = rng.roll(decision.agent);
                 self.space_card(decision.agent, roll);
                 self.discard_card(decision.agent, card);
@@ -355,7 +576,7 @@ impl GameState {
                 };
                 if let Side::US = side {
                     if self.has_effect(Side::USSR, Effect::Yuri) {
-                        self.vp -= 1;
+                        self.add_vp(-1);
                     }
                 }
                 let mut roll = rng.roll(decision.agent);
@@ -374,6 +595,7 @@ impl GameState {
                 let mut ops = decision.quantity;
                 if self.china && !Region::Asia.has_country(choice) {
                     ops -= 1;
+                    self.record_flags();
                     self.china = false;
                 }
                 let success = self.take_coup(side, choice, ops, roll, free_coup);
@@ -409,10 +631,12 @@ impl GameState {
                 let cost = self.add_influence(side, choice);
                 if self.china && !Region::Asia.has_country(choice) {
                     decision.quantity -= 1;
+                    self.record_flags();
                     self.china = false;
                 }
                 if self.vietnam && !Region::SoutheastAsia.has_country(choice) {
                     decision.quantity -= 1;
+                    self.record_flags();
                     self.vietnam = false;
                 }
                 if cost == 2 {
@@ -453,7 +677,9 @@ impl GameState {
                 }
             }
             Action::RecoverCard => {
-                self.deck.recover_card(side, Card::from_index(choice));
+                let card = Card::from_index(choice);
+                self.move_card(card, Pile::Discard, Pile::Hand(side))
+                    .unwrap();
             }
             Action::ChooseCard => {
                 let event = self.current_event().expect("Some event");
@@ -464,18 +690,16 @@ impl GameState {
                         if chosen_card.side() == side {
                             // Opponent Card -> Ops
                             let ops = chosen_card.modified_ops(side.opposite(), self);
-                            let dec = Decision::with_quantity(
-                                side.opposite(),
-                                Action::ConductOps,
-                                &[],
-                                ops,
-                            );
+                            let dec = Decision::conduct_ops(side.opposite(), ops);
                             self.add_pending(dec);
                         } else {
                             // ME eventer side card, or neutral
                             let dec = Decision::new_event(side.opposite(), chosen_card);
                             self.add_pending(dec);
                         }
+                        // Missile Envy itself goes to the side that gave a card
+                        let pile = self.played_pile(Card::Missile_Envy);
+                        let _ = self.move_card(Card::Missile_Envy, pile, Pile::Hand(side));
                         self.add_effect(side, Effect::MissileEnvy);
                     }
                     Card::Grain_Sales => {
@@ -487,16 +711,17 @@ impl GameState {
                     Card::Ask_Not => {
                         if choice == 0 {
                             // Done discarding
-                            let hand = self.deck.us_hand_mut();
-                            let size = hand.len();
-                            hand.retain(|c| *c != Card::Dummy);
-                            for _ in 0..size - hand.len() {
-                                self.deck.draw_to_hand(rng, Side::US);
+                            let hand = Pile::Hand(Side::US);
+                            let mut count = 0;
+                            while let Ok(index) = self.deck.take(hand, Card::Dummy) {
+                                self.record(Delta::CardTaken(hand, index, Card::Dummy));
+                                count += 1;
                             }
+                            self.draw_to_hand(Side::US, count, rng);
                             decision.quantity = 1; // Done
                         } else {
                             let card = Card::from_index(choice);
-                            self.deck.play_card(Side::US, card).unwrap();
+                            self.discard_card(Side::US, card);
                             // We no longer know the hand (in full, at least)
                             if let Some(i) = self.effect_pos(Side::USSR, Effect::US_Hand_Revealed) {
                                 self.clear_effect(Side::USSR, i);
@@ -511,10 +736,12 @@ impl GameState {
                     Card::Our_Man_In_Tehran => {
                         if choice == 0 {
                             decision.quantity = 1;
+                            self.record_piles();
                             self.deck.reshuffle(rng);
                         } else {
                             let card = Card::from_index(choice);
-                            self.deck.discard_draw(card);
+                            self.move_card(card, Pile::Draw, Pile::Discard)
+                                .expect("Found card");
                         }
                     }
                     _ => unimplemented!(),
@@ -523,12 +750,13 @@ impl GameState {
             Action::ChangeDefcon => self.set_defcon(choice as i8),
             Action::BlockRegion => {
                 let regions = Region::major_regions();
+                self.record_flags();
                 self.chernobyl = Some(regions[choice]);
             }
             Action::DoubleInf => {
                 let (offset, _) = Region::SouthAmerica.low_high();
-                let country = &mut self.countries[offset + choice];
-                country.ussr *= 2;
+                let ussr = self.countries[offset + choice].ussr;
+                self.set_influence(Side::USSR, offset + choice, 2 * ussr);
             }
             Action::BeginAr
             | Action::EndAr
@@ -543,9 +771,9 @@ impl GameState {
     fn wwby(&mut self, safe: bool) {
         if let Some(pos) = self.effect_pos(Side::US, Effect::WWBY) {
             if !safe {
-                self.vp -= 3;
+                self.add_vp(-3);
             }
-            self.us_effects.swap_remove(pos);
+            self.clear_effect(Side::US, pos);
         }
     }
     pub fn us_effects(&self) -> &[Effect] {
@@ -576,97 +804,84 @@ impl GameState {
         };
         vec.iter().position(|e| *e == effect)
     }
-    pub fn add_effect(&mut self, side: Side, effect: Effect) {
-        // Assert that this event shouldn't only go to the opponent
-        assert_ne!(side.opposite(), effect.allowed_side());
-        let vec = match side {
+    fn effects_mut(&mut self, side: Side) -> &mut Vec<Effect> {
+        match side {
             Side::US => &mut self.us_effects,
             Side::USSR => &mut self.ussr_effects,
             _ => unimplemented!(),
-        };
-        vec.push(effect);
+        }
+    }
+    pub fn add_effect(&mut self, side: Side, effect: Effect) {
+        // Assert that this event shouldn't only go to the opponent
+        assert_ne!(side.opposite(), effect.allowed_side());
+        self.effects_mut(side).push(effect);
+        self.record(Delta::EffectAdded(side));
+        self.emit(GameEvent::EffectAdded { side, effect });
     }
     pub fn clear_effect(&mut self, side: Side, index: usize) {
-        let vec = match side {
-            Side::US => &mut self.us_effects,
-            Side::USSR => &mut self.ussr_effects,
-            _ => unimplemented!(),
-        };
-        vec.swap_remove(index);
+        let effect = self.effects_mut(side).remove(index);
+        self.record(Delta::EffectCleared(side, index, effect));
+        self.emit(GameEvent::EffectCleared { side, effect });
     }
     pub fn is_controlled<T: Into<usize>>(&self, side: Side, country: T) -> bool {
         side == self.countries[country.into()].controller()
     }
-    pub fn control(&mut self, side: Side, country: CName) {
-        let c = &mut self.countries[country as usize];
-        match side {
-            Side::US => {
-                c.us = std::cmp::max(c.us, c.ussr + c.stability);
-            }
-            Side::USSR => {
-                c.ussr = std::cmp::max(c.ussr, c.us + c.stability);
-            }
+    /// Sets the side's influence in the country, the one place influence
+    /// changes.
+    pub fn set_influence<T: Into<usize>>(&mut self, side: Side, country: T, value: i8) {
+        let country = country.into();
+        let c = &mut self.countries[country];
+        let (us, ussr) = (c.us, c.ussr);
+        let change = match side {
+            Side::US => value - std::mem::replace(&mut c.us, value),
+            Side::USSR => value - std::mem::replace(&mut c.ussr, value),
             Side::Neutral => unimplemented!(),
+        };
+        self.record(Delta::Influence { country, us, ussr });
+        if change != 0 {
+            self.emit(GameEvent::Influence {
+                side,
+                country: CName::from_index(country),
+                change,
+            });
         }
     }
+    pub fn change_influence<T: Into<usize>>(&mut self, side: Side, country: T, change: i8) {
+        let country = country.into();
+        let value = self.countries[country].influence(side) + change;
+        self.set_influence(side, country, value);
+    }
+    pub fn control(&mut self, side: Side, country: CName) {
+        let c = &self.countries[country as usize];
+        let opp = c.influence(side.opposite());
+        let value = std::cmp::max(c.influence(side), opp + c.stability);
+        self.set_influence(side, country, value);
+    }
     pub fn remove_influence(&mut self, side: Side, country_index: usize, num: i8) {
-        let c = &mut self.countries[country_index];
         // Require checking for influence prior to this step
-        match side {
-            Side::US => c.us -= num,
-            Side::USSR => c.ussr -= num,
-            _ => unimplemented!(),
-        }
+        self.change_influence(side, country_index, -num);
     }
     pub fn add_influence(&mut self, side: Side, country_index: usize) -> i8 {
-        let c = &mut self.countries[country_index];
-        let controller = c.controller();
-        match side {
-            Side::US => {
-                c.us += 1;
-                if controller == Side::USSR {
-                    2
-                } else {
-                    1
-                }
-            }
-            Side::USSR => {
-                c.ussr += 1;
-                if controller == Side::US {
-                    2
-                } else {
-                    1
-                }
-            }
-            Side::Neutral => unimplemented!(),
+        let controller = self.countries[country_index].controller();
+        self.change_influence(side, country_index, 1);
+        if controller == side.opposite() {
+            2
+        } else {
+            1
         }
     }
     pub fn remove_all<T: Into<usize>>(&mut self, side: Side, country: T) {
-        let c = &mut self.countries[country.into()];
-        match side {
-            Side::US => {
-                c.us = 0;
-            }
-            Side::USSR => {
-                c.ussr = 0;
-            }
-            Side::Neutral => unimplemented!(),
-        }
+        self.set_influence(side, country, 0);
     }
     /// Clears all effects that are meant to only last for a single turn.
     pub fn turn_effect_clear(&mut self) {
-        self.us_effects = self
-            .us_effects
-            .iter()
-            .copied()
-            .filter(|e| e.permanent())
-            .collect();
-        self.ussr_effects = self
-            .ussr_effects
-            .iter()
-            .copied()
-            .filter(|e| e.permanent())
-            .collect();
+        for &side in [Side::US, Side::USSR].iter() {
+            for i in (0..self.effects_mut(side).len()).rev() {
+                if !self.effects_mut(side)[i].permanent() {
+                    self.clear_effect(side, i);
+                }
+            }
+        }
     }
     /// Returns which period of the war the game is in
     pub fn period(&self) -> Period {
@@ -693,32 +908,40 @@ impl GameState {
     pub fn war_target(&mut self, war_side: Side, country_index: usize, mut roll: i8) -> bool {
         let adjacent = self.adjacent_controlled(country_index, war_side.opposite());
         roll -= adjacent;
-        if roll >= 4 {
+        let success = roll >= 4;
+        self.emit(GameEvent::War {
+            side: war_side,
+            country: CName::from_index(country_index),
+            roll,
+            success,
+        });
+        if success {
             self.war_flip(war_side, country_index);
-            true
-        } else {
-            false
         }
+        success
     }
     fn war_flip(&mut self, war_side: Side, country_index: usize) {
-        let c = &mut self.countries[country_index];
-        match war_side {
-            Side::US => {
-                let opp = c.ussr;
-                c.ussr = 0;
-                c.us += opp;
-            }
-            Side::USSR => {
-                let opp = c.us;
-                c.us = 0;
-                c.ussr += opp;
-            }
-            Side::Neutral => unimplemented!(),
-        }
+        let opp = self.countries[country_index].influence(war_side.opposite());
+        self.set_influence(war_side.opposite(), country_index, 0);
+        self.change_influence(war_side, country_index, opp);
     }
     pub fn add_mil_ops(&mut self, side: Side, amount: i8) {
         let mil_ops = self.mil_ops[side as usize];
-        self.mil_ops[side as usize] = std::cmp::min(5, mil_ops + amount);
+        self.set_mil_ops(side, std::cmp::min(5, mil_ops + amount));
+    }
+    pub fn set_mil_ops(&mut self, side: Side, value: i8) {
+        let old = std::mem::replace(&mut self.mil_ops[side as usize], value);
+        self.record(Delta::MilOps(side, old));
+        if value != old {
+            self.emit(GameEvent::MilOps { side, value });
+        }
+    }
+    pub fn set_space(&mut self, side: Side, level: i8) {
+        let old = std::mem::replace(&mut self.space[side as usize], level);
+        self.record(Delta::Space(side, old));
+        if level != old {
+            self.emit(GameEvent::Space { side, level });
+        }
     }
     pub fn take_realign(&mut self, country_index: usize, mut us_roll: i8, mut ussr_roll: i8) {
         // This should include superpowers as well
@@ -732,50 +955,52 @@ impl GameState {
         if self.has_effect(Side::USSR, Effect::IranContra) {
             us_roll -= 1;
         }
-        let country = &mut self.countries[country_index];
-        match country.greater_influence() {
+        match self.countries[country_index].greater_influence() {
             Side::US => us_roll += 1,
             Side::USSR => ussr_roll += 1,
             Side::Neutral => {}
         }
+        self.emit(GameEvent::Realignment {
+            country: CName::from_index(country_index),
+            us_roll,
+            ussr_roll,
+        });
+        let country = &self.countries[country_index];
         if us_roll > ussr_roll {
             let diff = us_roll - ussr_roll;
-            country.ussr = std::cmp::max(0, country.ussr - diff);
+            let ussr = std::cmp::max(0, country.ussr - diff);
+            self.set_influence(Side::USSR, country_index, ussr);
         } else if ussr_roll > us_roll {
             let diff = ussr_roll - us_roll;
-            country.us = std::cmp::max(0, country.us - diff);
+            let us = std::cmp::max(0, country.us - diff);
+            self.set_influence(Side::US, country_index, us);
         }
     }
     pub fn take_coup(&mut self, side: Side, c_index: usize, ops: i8, roll: i8, free: bool) -> bool {
-        let c = &mut self.countries[c_index];
+        let c = &self.countries[c_index];
         let delta = std::cmp::max(0, ops + roll - 2 * c.stability);
-        match side {
-            Side::US => {
-                let left = delta - c.ussr;
-                if left > 0 {
-                    c.ussr = 0;
-                    c.us += left;
-                } else {
-                    c.ussr -= delta;
-                }
-            }
-            Side::USSR => {
-                let left = delta - c.us;
-                if left > 0 {
-                    c.us = 0;
-                    c.ussr += left;
-                } else {
-                    c.us -= delta;
-                }
-            }
-            Side::Neutral => unimplemented!(),
+        let bg = c.bg;
+        let opp = side.opposite();
+        let left = delta - c.influence(opp);
+        self.emit(GameEvent::Coup {
+            side,
+            country: CName::from_index(c_index),
+            roll,
+            ops,
+            result: delta,
+        });
+        if left > 0 {
+            self.set_influence(opp, c_index, 0);
+            self.change_influence(side, c_index, left);
+        } else {
+            self.change_influence(opp, c_index, -delta);
         }
-        if c.bg {
+        if bg {
             self.set_defcon(self.defcon() - 1);
         }
         if !free {
-            let x = side as usize;
-            self.mil_ops[x] = std::cmp::max(5, self.mil_ops[x] + ops);
+            let mil_ops = self.mil_ops[side as usize];
+            self.set_mil_ops(side, std::cmp::min(5, mil_ops + ops));
         }
         // Return true if we removed any influence
         delta > 0
@@ -842,9 +1067,15 @@ impl GameState {
             7 => roll <= 2,
             _ => unimplemented!(),
         };
+        self.emit(GameEvent::SpaceRace {
+            side,
+            roll,
+            success,
+        });
+        self.record(Delta::SpaceAttempts(side, self.space_attempts[me]));
         self.space_attempts[me] += 1;
         if success {
-            self.space[me] += 1;
+            self.set_space(side, self.space[me] + 1);
             let first = self.space[me] > self.space[opp];
             let points = match self.space[me] {
                 1 => {
@@ -885,8 +1116,8 @@ impl GameState {
                 _ => 0,
             };
             match side {
-                Side::US => self.vp += points,
-                Side::USSR => self.vp -= points,
+                Side::US => self.add_vp(points),
+                Side::USSR => self.add_vp(-points),
                 _ => unimplemented!(),
             }
         }
@@ -956,7 +1187,9 @@ impl GameState {
                         set.insert(*name as usize);
                     }
                 }
-                vec.extend(set.iter());
+                let mut set: Vec<_> = set.into_iter().collect();
+                set.sort_unstable();
+                vec.extend(set);
             } else {
                 vec.extend(valid(&EUROPE));
             }
@@ -964,67 +1197,30 @@ impl GameState {
         vec
     }
     pub fn legal_influence(&self, agent: Side, ops: i8) -> Vec<usize> {
+        assert!(ops > 0);
         let china = self.china;
         let vietnam = self.vietnam;
-        let real_ops = ops - (china as i8) - (vietnam as i8);
         let mut a = access(self, agent);
         if let (Side::USSR, Some(region)) = (agent, self.chernobyl) {
             a = a.into_iter().filter(|x| !region.has_country(*x)).collect();
         }
-        if real_ops > 1 {
-            // Doesn't need to tap into bonus influence
-            a
-        } else if ops <= 1 {
-            // Cannot break control anywhere
-            assert!(ops > 0);
-            a.into_iter()
-                .filter(|x| {
-                    let c = &self.countries[*x];
-                    c.controller() != agent.opposite()
-                })
-                .collect()
-        } else if china {
-            // If China is in play, vietnam revolts is irrelevant for legality
-            // since no action costs more than 2 ops and Southeast Asia
-            // is a subset of Asia
-            if ops >= 2 {
-                // Can break across Asia, but cannot elsewhere
-                a.into_iter()
-                    .filter(|x| {
-                        let c = &self.countries[*x];
-                        c.controller() != agent.opposite() || Region::Asia.has_country(*x)
-                    })
-                    .collect()
-            } else {
-                // Can only place in uncontrolled Asia
-                a.into_iter()
-                    .filter(|x| {
-                        let c = &self.countries[*x];
-                        c.controller() != agent.opposite() && Region::Asia.has_country(*x)
-                    })
-                    .collect()
-            }
-        } else if vietnam {
-            if ops >= 2 {
-                // Can break in SE Asia, but cannot elsewhere
-                a.into_iter()
-                    .filter(|x| {
-                        let c = &self.countries[*x];
-                        c.controller() != agent.opposite() || Region::SoutheastAsia.has_country(*x)
-                    })
-                    .collect()
-            } else {
-                // Can only place in uncontrolled SE Asia
-                a.into_iter()
-                    .filter(|x| {
-                        let c = &self.countries[*x];
-                        c.controller() != agent.opposite() && Region::SoutheastAsia.has_country(*x)
-                    })
-                    .collect()
-            }
-        } else {
-            unreachable!() // Todo figure out if this is actually unreachable
-        }
+        // Breaking control costs an extra op, as does losing the bonus of
+        // the China Card or Vietnam Revolts by placing outside its region
+        a.into_iter()
+            .filter(|&x| {
+                let mut cost = 1;
+                if self.countries[x].controller() == agent.opposite() {
+                    cost += 1;
+                }
+                if china && !Region::Asia.has_country(x) {
+                    cost += 1;
+                }
+                if vietnam && !Region::SoutheastAsia.has_country(x) {
+                    cost += 1;
+                }
+                cost <= ops
+            })
+            .collect()
     }
     pub fn legal_war(&self, side: Side) -> Allowed {
         if side == Side::USSR && self.has_effect(Side::US, Effect::Nato) {
@@ -1071,10 +1267,16 @@ impl GameState {
         self.current_event
     }
     pub fn set_event(&mut self, event: Card) {
-        self.current_event = Some(event);
+        let old = self.current_event.replace(event);
+        self.record(Delta::CurrentEvent(old));
     }
     pub fn clear_event(&mut self) {
-        self.current_event = None;
+        let old = self.current_event.take();
+        self.record(Delta::CurrentEvent(old));
+    }
+    fn push_pending(&mut self, decision: Decision) {
+        self.pending.push(decision);
+        self.record(Delta::PendingPushed);
     }
     pub fn add_pending(&mut self, decision: Decision) {
         // After
@@ -1082,57 +1284,84 @@ impl GameState {
         let side = decision.agent;
         match act {
             Action::Event => {
-                self.pending
-                    .push(Decision::new(Side::Neutral, Action::ClearEvent, &[]))
+                self.push_pending(Decision::new(Side::Neutral, Action::ClearEvent, &[]))
             }
-            Action::BeginAr => self
-                .pending
-                .push(Decision::new(Side::Neutral, Action::EndAr, &[])),
+            Action::BeginAr => self.push_pending(Decision::new(Side::Neutral, Action::EndAr, &[])),
             _ => {}
         }
 
-        self.pending.push(decision);
+        self.push_pending(decision);
 
         // Before
         match act {
             Action::Coup | Action::ConductOps => {
                 if self.has_effect(side, Effect::CubanMissileCrisis) {
                     let legal = self.legal_cuban(side);
-                    self.pending
-                        .push(Decision::new(side, Action::CubanMissile, legal));
+                    self.push_pending(Decision::new(side, Action::CubanMissile, legal));
                 }
             }
             _ => {}
         }
     }
     pub fn next_legal(&mut self) -> Vec<OutputIndex> {
+        let mut legal = Vec::new();
+        self.next_legal_into(&mut legal);
+        legal
+    }
+    /// Replaces the contents of `legal` with the legal actions, reusing its
+    /// allocation.
+    pub fn next_legal_into(&mut self, legal: &mut Vec<OutputIndex>) {
+        legal.clear();
         let d = self.pending.pop();
         if let Some(mut d) = d {
-            let encoded = d.encode(&self);
+            d.encode_into(self, legal);
             self.pending.push(d);
-            encoded
         } else {
-            vec![OutputIndex::pass()]
+            legal.push(OutputIndex::pass());
         }
     }
     pub fn pending(&self) -> &[Decision] {
         &self.pending
     }
     pub fn remove_pending(&mut self) -> Option<Decision> {
-        self.pending.pop()
+        let decision = self.pending.pop();
+        if let (Some(d), true) = (&decision, self.journaling()) {
+            self.record(Delta::PendingPopped(d.clone()));
+        }
+        decision
     }
     pub fn peek_pending(&self) -> Option<&Decision> {
         self.pending.last()
     }
-    pub fn peek_pending_mut(&mut self) -> Option<&mut Decision> {
-        self.pending.last_mut()
+    /// Returns the side whose input the state is waiting on. Bookkeeping
+    /// decisions, which belong to neither side, fall to the phasing side.
+    pub fn acting_side(&self) -> Side {
+        match self.peek_pending() {
+            Some(d) if d.agent != Side::Neutral => d.agent,
+            _ => self.side,
+        }
     }
     pub fn defectors_headline(&mut self) {
         let keep_pos = self.pending().iter().position(|d| d.agent == Side::US);
-        let us_hl = self.pending.swap_remove(keep_pos.unwrap());
+        let us_hl = self.pending[keep_pos.unwrap()].clone();
         self.clear_pending();
         self.add_pending(us_hl);
     }
+    /// Returns the headline choices of both sides, in the order they are made.
+    pub fn headline_order(&self) -> Vec<Decision> {
+        let us_hl = Decision::headline(Side::US, self);
+        let ussr_hl = Decision::headline(Side::USSR, self);
+        let us_space = self.space[Side::US as usize];
+        let ussr_space = self.space[Side::USSR as usize];
+
+        if ussr_space > us_space && ussr_space >= 4 {
+            // If ussr has the power, explicitly make US input their decision first
+            vec![ussr_hl, us_hl]
+        } else {
+            // Else assume as usual that the USSR is phasing first
+            vec![us_hl, ussr_hl]
+        }
+    }
     pub fn order_headlines(&mut self) {
         let priority = |x: &Decision| {
             let c = Card::from_index(x.allowed.try_slice().unwrap()[0]);
@@ -1148,20 +1377,24 @@ impl GameState {
         let (i2, e2) = iter.next().unwrap();
         if priority(e2) > priority(e1) {
             self.pending.swap(i1, i2);
+            self.record(Delta::PendingSwapped(i1, i2));
         }
     }
     pub fn set_pending(&mut self, pending: Vec<Decision>) {
         assert!(self.pending.is_empty());
-        self.pending = pending;
+        let old = std::mem::replace(&mut self.pending, pending);
+        self.record(Delta::Pending(old));
     }
     pub fn clear_pending(&mut self) {
-        self.pending.clear();
+        let old = std::mem::take(&mut self.pending);
+        self.record(Delta::Pending(old));
     }
     pub fn empty_pending(&self) -> bool {
         self.pending.is_empty()
     }
     pub fn set_limit(&mut self, limit: usize) {
-        self.restrict = Some(Restriction::Limit(limit));
+        let old = self.restrict.replace(Restriction::Limit(limit));
+        self.record(Delta::Restrict(old));
         // Todo restriction clear more nicely
     }
     pub fn mil_ops(&self, side: Side) -> i8 {
@@ -1197,18 +1430,31 @@ pub enum Period {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Win {
     Defcon(Side),
     Vp(Side),
     HeldScoring(Side),
+    /// Final scoring or Wargames left the VP track at 0.
+    Draw,
 }
 
 impl Win {
+    /// Returns the result of ending the game on the VP track as it stands.
+    pub fn from_vp(vp: i8) -> Win {
+        match vp {
+            0 => Win::Draw,
+            vp if vp > 0 => Win::Vp(Side::US),
+            _ => Win::Vp(Side::USSR),
+        }
+    }
+    /// Returns the winning side, which is `Side::Neutral` for a draw.
     pub fn winner(&self) -> Side {
         match self {
             Win::Defcon(s) => *s,
             Win::Vp(s) => *s,
             Win::HeldScoring(s) => *s,
+            Win::Draw => Side::Neutral,
         }
     }
 }
@@ -1260,6 +1506,21 @@ mod tests {
         //     assert!(!agent.legal_line(&mut s, vec![d.clone()], rng.clone()))
         // }
     }
+    #[test]
+    fn test_bonus_influence() {
+        let mut state = GameState::four_four_two();
+        state.china = true;
+        // The last op of the China Card only counts in Asia
+        let legal = state.legal_influence(Side::USSR, 1);
+        assert!(!legal.is_empty());
+        assert!(legal.iter().all(|&x| Region::Asia.has_country(x)));
+        state.vietnam = true;
+        state.control(Side::US, CName::NKorea);
+        let nkorea = CName::NKorea as usize;
+        // Breaking control outside Southeast Asia also gives up Vietnam Revolts
+        assert!(!state.legal_influence(Side::USSR, 2).contains(&nkorea));
+        assert!(state.legal_influence(Side::USSR, 3).contains(&nkorea));
+    }
     fn influence_in(country: CName) -> OutputIndex {
         OutputIndex::new(Action::Influence.offset() + country as usize)
     }
@@ -0,0 +1,119 @@
+use super::GameState;
+use crate::card::{Card, Effect};
+use crate::country::{CName, Region, Side};
+
+use std::fmt;
+
+/// Renders the board as text: the tracks, every region's countries, the
+/// effects in play and both hands.
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Turn {} AR {}, {:?} phasing | VP {:+} | DEFCON {}",
+            self.turn,
+            self.ar,
+            self.side,
+            self.vp,
+            self.defcon()
+        )?;
+        writeln!(
+            f,
+            "Space: US {} USSR {} | Mil ops: US {} USSR {}",
+            self.space[0], self.space[1], self.mil_ops[0], self.mil_ops[1]
+        )?;
+        let face = if self.deck.china_available(self.deck.china()) {
+            "face up"
+        } else {
+            "face down"
+        };
+        write!(f, "China Card: {:?} ({})", self.deck.china(), face)?;
+        if let Some(card) = self.current_event() {
+            write!(f, " | Resolving: {:?}", card)?;
+        }
+        writeln!(f)?;
+        for &region in Region::major_regions().iter() {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{:<16} {:>4} {:>2} {:>4} {:>4}  Control",
+                format!("{:?}", region),
+                "Stab",
+                "BG",
+                "US",
+                "USSR"
+            )?;
+            for i in region.all_countries() {
+                let c = &self.countries[i];
+                let control = match c.controller() {
+                    Side::Neutral => "",
+                    Side::US => "US",
+                    Side::USSR => "USSR",
+                };
+                let row = format!(
+                    "  {:<14} {:>4} {:>2} {:>4} {:>4}  {}",
+                    format!("{:?}", CName::from_index(i)),
+                    c.stability,
+                    if c.bg { "*" } else { "" },
+                    c.us,
+                    c.ussr,
+                    control
+                );
+                writeln!(f, "{}", row.trim_end())?;
+            }
+        }
+        writeln!(f)?;
+        let effects = [
+            (Side::US, self.us_effects()),
+            (Side::USSR, self.ussr_effects()),
+        ];
+        for (side, effects) in effects.iter() {
+            writeln!(f, "{:?} effects: {}", side, list::<Effect>(effects))?;
+        }
+        for &side in [Side::US, Side::USSR].iter() {
+            let hand = self.deck.hand(side);
+            writeln!(
+                f,
+                "{:?} hand ({}): {}",
+                side,
+                hand.len(),
+                list::<Card>(hand)
+            )?;
+        }
+        write!(
+            f,
+            "Deck: {} to draw, {} discarded, {} removed",
+            self.deck.draw_pile().len(),
+            self.deck.discard_pile().len() + self.deck.pending_discard().len(),
+            self.deck.removed().len()
+        )
+    }
+}
+
+fn list<T: fmt::Debug>(items: &[T]) -> String {
+    if items.is_empty() {
+        return "-".to_string();
+    }
+    let names: Vec<_> = items.iter().map(|x| format!("{:?}", x)).collect();
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board() {
+        let mut state = GameState::four_four_two();
+        state.deck.us_hand_mut().push(Card::Containment);
+        let board = state.to_string();
+        let italy = board.lines().find(|l| l.trim().starts_with("Italy"));
+        assert_eq!(italy.unwrap().split_whitespace().last(), Some("US"));
+        assert!(board.contains("Europe"));
+        assert!(board.contains("US hand (1): Containment"));
+        assert!(board.contains("USSR effects: -"));
+        let countries = crate::country::NUM_COUNTRIES - 2;
+        let rows = board.lines().filter(|l| l.starts_with("  ")).count();
+        assert_eq!(rows, countries);
+    }
+}
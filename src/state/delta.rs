@@ -0,0 +1,165 @@
+use super::GameState;
+use crate::action::{Decision, Restriction};
+use crate::card::{Card, Effect, Pile};
+use crate::country::{Region, Side};
+use crate::game::EndReason;
+
+/// A single change to a `GameState`, holding what is needed to take it back.
+/// Deltas are recorded where the state is changed, and must be reverted
+/// newest first.
+#[derive(Clone, Debug)]
+pub enum Delta {
+    Influence {
+        country: usize,
+        us: i8,
+        ussr: i8,
+    },
+    Vp(i8),
+    Defcon(i8),
+    Turn(i8),
+    Ar(i8),
+    Side(Side),
+    Space(Side, i8),
+    MilOps(Side, i8),
+    SpaceAttempts(Side, i8),
+    /// An effect was added to the end of the side's effects.
+    EffectAdded(Side),
+    /// The effect was removed from the side's effects at the index.
+    EffectCleared(Side, usize, Effect),
+    /// A card was put on top of the pile.
+    CardPut(Pile),
+    /// The card was taken out of the pile at the index.
+    CardTaken(Pile, usize, Card),
+    China {
+        holder: Side,
+        face_up: bool,
+    },
+    /// The draw and discard piles before the RNG drew from or reshuffled
+    /// them, which it may do in any order.
+    Piles {
+        draw: Vec<Card>,
+        discard: Vec<Card>,
+    },
+    Restrict(Option<Restriction>),
+    CurrentEvent(Option<Card>),
+    Flags {
+        vietnam: bool,
+        china: bool,
+        iron_lady: bool,
+        chernobyl: Option<Region>,
+    },
+    EndReason(Option<EndReason>),
+    /// A decision was pushed onto the pending stack.
+    PendingPushed,
+    /// A decision was inserted into the pending stack at the index.
+    PendingInserted(usize),
+    /// The decision was popped off the pending stack.
+    PendingPopped(Decision),
+    /// The pending decisions at the two indices were swapped.
+    PendingSwapped(usize, usize),
+    /// The whole pending stack was replaced.
+    Pending(Vec<Decision>),
+}
+
+impl GameState {
+    /// Starts or stops recording deltas as the state changes.
+    pub(crate) fn set_journal(&mut self, enabled: bool) {
+        self.journal = if enabled { Some(Vec::new()) } else { None };
+    }
+    /// Records the delta, if recording.
+    pub(crate) fn record(&mut self, delta: Delta) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push(delta);
+        }
+    }
+    /// Whether deltas are being recorded, so that callers can skip the work
+    /// of building costly ones.
+    pub(crate) fn journaling(&self) -> bool {
+        self.journal.is_some()
+    }
+    /// Takes the deltas recorded so far, oldest first.
+    pub(crate) fn take_journal(&mut self) -> Vec<Delta> {
+        match self.journal.as_mut() {
+            Some(journal) => std::mem::take(journal),
+            None => Vec::new(),
+        }
+    }
+    /// Records the per-action flags before one of them changes.
+    pub(crate) fn record_flags(&mut self) {
+        if self.journaling() {
+            self.record(Delta::Flags {
+                vietnam: self.vietnam,
+                china: self.china,
+                iron_lady: self.iron_lady,
+                chernobyl: self.chernobyl,
+            });
+        }
+    }
+    /// Records the draw and discard piles before the RNG changes them.
+    pub(crate) fn record_piles(&mut self) {
+        if self.journaling() {
+            self.record(Delta::Piles {
+                draw: self.deck.draw_pile().clone(),
+                discard: self.deck.discard_pile().clone(),
+            });
+        }
+    }
+    /// Takes back the given deltas, as returned by `take_journal`.
+    pub fn revert(&mut self, deltas: Vec<Delta>) {
+        for d in deltas.into_iter().rev() {
+            match d {
+                Delta::Influence { country, us, ussr } => {
+                    let c = &mut self.countries[country];
+                    c.us = us;
+                    c.ussr = ussr;
+                }
+                Delta::Vp(x) => self.vp = x,
+                Delta::Defcon(x) => self.defcon = x,
+                Delta::Turn(x) => self.turn = x,
+                Delta::Ar(x) => self.ar = x,
+                Delta::Side(x) => self.side = x,
+                Delta::Space(side, x) => self.space[side as usize] = x,
+                Delta::MilOps(side, x) => self.mil_ops[side as usize] = x,
+                Delta::SpaceAttempts(side, x) => self.space_attempts[side as usize] = x,
+                Delta::EffectAdded(side) => {
+                    self.effects_mut(side).pop();
+                }
+                Delta::EffectCleared(side, index, effect) => {
+                    self.effects_mut(side).insert(index, effect);
+                }
+                Delta::CardPut(pile) => {
+                    self.deck.pile_mut(pile).pop();
+                }
+                Delta::CardTaken(pile, index, card) => self.deck.untake(pile, index, card),
+                Delta::China { holder, face_up } => self.deck.set_china(holder, face_up),
+                Delta::Piles { draw, discard } => {
+                    *self.deck.draw_pile_mut() = draw;
+                    *self.deck.discard_pile_mut() = discard;
+                }
+                Delta::Restrict(x) => self.restrict = x,
+                Delta::CurrentEvent(x) => self.current_event = x,
+                Delta::Flags {
+                    vietnam,
+                    china,
+                    iron_lady,
+                    chernobyl,
+                } => {
+                    self.vietnam = vietnam;
+                    self.china = china;
+                    self.iron_lady = iron_lady;
+                    self.chernobyl = chernobyl;
+                }
+                Delta::EndReason(x) => self.end_reason = x,
+                Delta::PendingPushed => {
+                    self.pending.pop();
+                }
+                Delta::PendingInserted(index) => {
+                    self.pending.remove(index);
+                }
+                Delta::PendingPopped(d) => self.pending.push(d),
+                Delta::PendingSwapped(i, j) => self.pending.swap(i, j),
+                Delta::Pending(x) => self.pending = x,
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use super::GameState;
+use crate::action::Action;
+use crate::card::{Card, Effect};
+use crate::country::{CName, Side};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Something that happened in a game, as recorded by `Game::set_event_log`.
+///
+/// Events are logged as they happen: the cards played, the dice rolled and
+/// each change to the board and the cards.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameEvent {
+    /// A card was played from hand, for the event, ops or the space race.
+    CardPlayed {
+        side: Side,
+        card: Card,
+        action: Action,
+    },
+    /// A coup with its modified roll and its result, the influence removed
+    /// from the opponent and then added for the side.
+    Coup {
+        side: Side,
+        country: CName,
+        roll: i8,
+        ops: i8,
+        result: i8,
+    },
+    /// A realignment with the modified rolls of both sides.
+    Realignment {
+        country: CName,
+        us_roll: i8,
+        ussr_roll: i8,
+    },
+    War {
+        side: Side,
+        country: CName,
+        roll: i8,
+        success: bool,
+    },
+    SpaceRace {
+        side: Side,
+        roll: i8,
+        success: bool,
+    },
+    Influence {
+        side: Side,
+        country: CName,
+        change: i8,
+    },
+    Vp {
+        change: i8,
+        total: i8,
+    },
+    Defcon {
+        from: i8,
+        to: i8,
+    },
+    Space {
+        side: Side,
+        level: i8,
+    },
+    MilOps {
+        side: Side,
+        value: i8,
+    },
+    EffectAdded {
+        side: Side,
+        effect: Effect,
+    },
+    EffectCleared {
+        side: Side,
+        effect: Effect,
+    },
+    /// A card entered the hand, whether drawn or taken back.
+    CardDrawn {
+        side: Side,
+        card: Card,
+    },
+    CardDiscarded {
+        card: Card,
+    },
+    CardRemoved {
+        card: Card,
+    },
+}
+
+impl GameState {
+    /// Starts or stops recording the events emitted while resolving actions.
+    pub(crate) fn set_recording(&mut self, enabled: bool) {
+        self.events = if enabled { Some(Vec::new()) } else { None };
+    }
+    /// Records the event, if recording.
+    pub(crate) fn emit(&mut self, event: GameEvent) {
+        if let Some(events) = self.events.as_mut() {
+            events.push(event);
+        }
+    }
+    /// Takes the events emitted so far.
+    pub(crate) fn take_events(&mut self) -> Vec<GameEvent> {
+        match self.events.as_mut() {
+            Some(events) => std::mem::take(events),
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use super::GameState;
+use crate::action::{Action, Decision};
+use crate::card::{Card, Effect, NUM_EFFECTS};
+use crate::country::{CName, Side};
+use crate::game::RuleSet;
+use crate::record::{find_card, find_country};
+
+/// Names of the space separated fields of the notation, in order.
+const FIELDS: [&str; 17] = [
+    "turn",
+    "vp",
+    "defcon",
+    "space",
+    "mil_ops",
+    "china",
+    "influence",
+    "us_effects",
+    "ussr_effects",
+    "us_hand",
+    "ussr_hand",
+    "pending_discard",
+    "discard",
+    "removed",
+    "draw",
+    "space_attempts",
+    "rules",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum NotationError {
+    /// The notation has the wrong number of fields.
+    FieldCount(usize),
+    /// The named field could not be parsed.
+    BadField(&'static str, String),
+    /// The state is in the middle of an action, which the notation cannot
+    /// describe. Names the part of the state that shows it.
+    InProgress(&'static str),
+}
+
+impl GameState {
+    /// Writes the position as a single line of space separated fields:
+    ///
+    /// ```text
+    /// turn/ar/phasing vp defcon space mil_ops china influence us_effects
+    /// ussr_effects us_hand ussr_hand pending_discard discard removed draw
+    /// space_attempts rules
+    /// ```
+    ///
+    /// Paired fields such as `space` are written `us/ussr`. The China Card is
+    /// its holder followed by `+` if face up or `-` if face down. Influence
+    /// lists every country with any influence as `Country:us/ussr`. Effects
+    /// and card piles are comma separated `Debug` names, or `-` if empty, and
+    /// the draw pile is listed from the bottom up. The rules are written
+    /// `optional_cards/bonus_influence/china_holder/bonus_stability_cap`,
+    /// with `+` or `-` for the switches.
+    ///
+    /// Only positions waiting for headlines to be chosen or for an action
+    /// round to begin can be written, so anything in the middle of an action
+    /// is an `InProgress` error.
+    pub fn to_notation(&self) -> Result<String, NotationError> {
+        self.check_notation()?;
+        let slash = |x: [i8; 2]| format!("{}/{}", x[0], x[1]);
+        let influence: Vec<_> = self
+            .countries
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.us != 0 || c.ussr != 0)
+            .map(|(i, c)| format!("{:?}:{}/{}", CName::from_index(i), c.us, c.ussr))
+            .collect();
+        let china = self.deck.china();
+        let china_up = switch(self.deck.china_available(china));
+        let fields = [
+            format!("{}/{}/{:?}", self.turn, self.ar, self.side),
+            self.vp.to_string(),
+            self.defcon.to_string(),
+            slash(self.space),
+            slash(self.mil_ops),
+            format!("{:?}{}", china, china_up),
+            list(influence),
+            list(self.us_effects.iter().map(|e| format!("{:?}", e)).collect()),
+            list(
+                self.ussr_effects
+                    .iter()
+                    .map(|e| format!("{:?}", e))
+                    .collect(),
+            ),
+            cards(self.deck.us_hand()),
+            cards(self.deck.ussr_hand()),
+            cards(self.deck.pending_discard()),
+            cards(self.deck.discard_pile()),
+            cards(self.deck.removed()),
+            cards(self.deck.draw_pile()),
+            slash(self.space_attempts),
+            format!(
+                "{}/{}/{:?}/{}",
+                switch(self.rules.optional_cards),
+                self.rules.bonus_influence,
+                self.rules.china_holder,
+                switch(self.rules.bonus_stability_cap)
+            ),
+        ];
+        Ok(fields.join(" "))
+    }
+    /// Reads a position written by `to_notation`. Card and country names may
+    /// be abbreviated to any unique prefix, as in record files, and the draw
+    /// pile may be given as `*` for every card in play that is not elsewhere.
+    pub fn from_notation(s: &str) -> Result<GameState, NotationError> {
+        let fields: Vec<_> = s.split_whitespace().collect();
+        if fields.len() != FIELDS.len() {
+            return Err(NotationError::FieldCount(fields.len()));
+        }
+        let mut state = GameState::new();
+        let mut turn = fields[0].split('/');
+        let err = |i: usize| NotationError::BadField(FIELDS[i], fields[i].to_string());
+        state.turn = number(turn.next()).ok_or_else(|| err(0))?;
+        state.ar = number(turn.next()).ok_or_else(|| err(0))?;
+        state.side = side(turn.next().unwrap_or("")).ok_or_else(|| err(0))?;
+        state.vp = number(Some(fields[1])).ok_or_else(|| err(1))?;
+        state.defcon = number(Some(fields[2])).ok_or_else(|| err(2))?;
+        state.space = pair(fields[3]).ok_or_else(|| err(3))?;
+        state.mil_ops = pair(fields[4]).ok_or_else(|| err(4))?;
+        let (holder, up) = fields[5].split_at(fields[5].len().saturating_sub(1));
+        let holder = side(holder).ok_or_else(|| err(5))?;
+        match up {
+            "+" => state.deck.set_china(holder, true),
+            "-" => state.deck.set_china(holder, false),
+            _ => return Err(err(5)),
+        }
+        for c in state.countries.iter_mut() {
+            c.us = 0;
+            c.ussr = 0;
+        }
+        for x in split(fields[6]) {
+            let (name, inf) = x.split_at(x.find(':').ok_or_else(|| err(6))?);
+            let index = find_country(name).ok_or_else(|| err(6))? as usize;
+            let [us, ussr] = pair(&inf[1..]).ok_or_else(|| err(6))?;
+            state.countries[index].us = us;
+            state.countries[index].ussr = ussr;
+        }
+        state.us_effects = effects(fields[7]).ok_or_else(|| err(7))?;
+        state.ussr_effects = effects(fields[8]).ok_or_else(|| err(8))?;
+        // The deck follows the rules, so they come before the card piles
+        state.set_rules(rules(fields[16]).ok_or_else(|| err(16))?);
+        let piles: Result<Vec<_>, _> = (9..14)
+            .map(|i| card_list(fields[i]).ok_or_else(|| err(i)))
+            .collect();
+        let mut piles = piles?.into_iter();
+        let deck = &mut state.deck;
+        *deck.us_hand_mut() = piles.next().unwrap();
+        *deck.ussr_hand_mut() = piles.next().unwrap();
+        *deck.pending_discard_mut() = piles.next().unwrap();
+        *deck.discard_pile_mut() = piles.next().unwrap();
+        *deck.removed_mut() = piles.next().unwrap();
+        let draw = if fields[14] == "*" {
+            state.remaining_cards()
+        } else {
+            card_list(fields[14]).ok_or_else(|| err(14))?
+        };
+        *state.deck.draw_pile_mut() = draw;
+        state.space_attempts = pair(fields[15]).ok_or_else(|| err(15))?;
+        state.add_start_pending();
+        Ok(state)
+    }
+    /// Adds the decisions a position of the notation starts with: choosing
+    /// headlines, or beginning the action round of the phasing side.
+    fn add_start_pending(&mut self) {
+        if self.ar > 0 {
+            self.add_pending(Decision::begin_ar(self.side));
+        } else if self.turn > 0 {
+            let headlines = self.headline_order();
+            self.set_pending(headlines);
+        }
+    }
+    fn check_notation(&self) -> Result<(), NotationError> {
+        let shape = |pending: &[Decision]| -> Vec<(Side, Action, i8)> {
+            pending
+                .iter()
+                .map(|d| (d.agent, d.action, d.quantity))
+                .collect()
+        };
+        let mut start = self.clone();
+        start.clear_pending();
+        start.add_start_pending();
+        let in_progress = if self.current_event.is_some() {
+            "current_event"
+        } else if self.restrict.is_some() {
+            "restrict"
+        } else if self.vietnam || self.china || self.iron_lady || self.chernobyl.is_some() {
+            "flags"
+        } else if self.end_reason.is_some() {
+            "end_reason"
+        } else if shape(&self.pending) != shape(&start.pending) {
+            "pending"
+        } else {
+            return Ok(());
+        };
+        Err(NotationError::InProgress(in_progress))
+    }
+    /// Returns the cards in play for the current period that are not in a
+    /// hand, a discard pile or removed.
+    fn remaining_cards(&self) -> Vec<Card> {
+        let mut pool = self.cards_in_play();
+        let deck = &self.deck;
+        let placed = deck
+            .us_hand()
+            .iter()
+            .chain(deck.ussr_hand().iter())
+            .chain(deck.pending_discard().iter())
+            .chain(deck.discard_pile().iter())
+            .chain(deck.removed().iter());
+        for c in placed {
+            if let Some(i) = pool.iter().position(|x| x == c) {
+                pool.remove(i);
+            }
+        }
+        pool
+    }
+}
+
+fn list(vec: Vec<String>) -> String {
+    if vec.is_empty() {
+        "-".to_string()
+    } else {
+        vec.join(",")
+    }
+}
+
+fn cards(vec: &[Card]) -> String {
+    list(vec.iter().map(|c| format!("{:?}", c)).collect())
+}
+
+fn split(s: &str) -> impl Iterator<Item = &str> {
+    s.split(',').filter(|x| *x != "-")
+}
+
+fn number(s: Option<&str>) -> Option<i8> {
+    s?.parse().ok()
+}
+
+fn pair(s: &str) -> Option<[i8; 2]> {
+    let mut iter = s.split('/');
+    let x = [number(iter.next())?, number(iter.next())?];
+    if iter.next().is_none() {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+fn switch(on: bool) -> &'static str {
+    if on {
+        "+"
+    } else {
+        "-"
+    }
+}
+
+fn rules(s: &str) -> Option<RuleSet> {
+    let parts: Vec<_> = s.split('/').collect();
+    let on = |s: &str| match s {
+        "+" => Some(true),
+        "-" => Some(false),
+        _ => None,
+    };
+    match parts.as_slice() {
+        [optional, bonus, china, cap] => Some(RuleSet {
+            optional_cards: on(optional)?,
+            bonus_influence: number(Some(bonus))?,
+            china_holder: side(china)?,
+            bonus_stability_cap: on(cap)?,
+        }),
+        _ => None,
+    }
+}
+
+fn side(s: &str) -> Option<Side> {
+    match s {
+        "US" => Some(Side::US),
+        "USSR" => Some(Side::USSR),
+        _ => None,
+    }
+}
+
+fn card_list(s: &str) -> Option<Vec<Card>> {
+    split(s)
+        .map(|name| match name {
+            "Dummy" => Some(Card::Dummy),
+            _ => find_card(name),
+        })
+        .collect()
+}
+
+fn effects(s: &str) -> Option<Vec<Effect>> {
+    split(s)
+        .map(|name| {
+            (0..NUM_EFFECTS)
+                .map(Effect::from_index)
+                .find(|e| format!("{:?}", e) == name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Deck;
+
+    #[test]
+    fn test_round_trip() {
+        let mut state = GameState::four_four_two();
+        state.turn = 4;
+        state.ar = 2;
+        state.side = Side::US;
+        state.vp = -3;
+        state.set_defcon(3);
+        state.space = [2, 1];
+        state.add_mil_ops(Side::USSR, 2);
+        state.add_effect(Side::US, Effect::Containment);
+        state.add_effect(Side::USSR, Effect::US_Scoring_Revealed);
+        state.deck.play_china();
+        state.deck.us_hand_mut().extend(vec![Card::NATO, Card::Che]);
+        state.deck.ussr_hand_mut().push(Card::Chernobyl);
+        state.add_pending(Decision::begin_ar(Side::US));
+        let text = state.to_notation().unwrap();
+        let parsed = GameState::from_notation(&text).unwrap();
+        assert_eq!(parsed.to_notation().unwrap(), text);
+        assert_eq!(parsed.deck, state.deck);
+        assert_eq!(parsed.peek_pending().unwrap().action, Action::BeginAr);
+        assert_eq!(parsed.defcon(), 3);
+        assert!(parsed.has_effect(Side::USSR, Effect::US_Scoring_Revealed));
+        let italy = &parsed.countries[CName::Italy as usize];
+        assert_eq!((italy.us, italy.ussr), (4, 0));
+    }
+
+    #[test]
+    fn test_fixture() {
+        let text = concat!(
+            "1/0/USSR 0 5 0/0 0/0 USSR+ Turkey:0/2,Italy:4/0,Poland:0/4,USSR:0/6 ",
+            "- - Nato,Asia_Sc - - - Duck * 0/0 +/2/USSR/+"
+        );
+        let state = GameState::from_notation(text).unwrap();
+        assert_eq!(state.pending().len(), 2);
+        assert!(state
+            .pending()
+            .iter()
+            .all(|d| d.action == Action::ChooseCard));
+        assert_eq!(state.countries[CName::Turkey as usize].ussr, 2);
+        assert_eq!(state.countries[CName::Syria as usize].ussr, 0);
+        assert_eq!(state.deck.us_hand(), &vec![Card::NATO, Card::Asia_Scoring]);
+        assert_eq!(state.deck.removed(), &vec![Card::Duck_and_Cover]);
+        let mut all = Deck::new().draw_pile().clone();
+        all.retain(|&c| c != Card::NATO && c != Card::Asia_Scoring && c != Card::Duck_and_Cover);
+        assert_eq!(state.deck.draw_pile(), &all);
+        assert!(GameState::from_notation("1/0/USSR 0 5").is_err());
+        let bad = text.replace("Poland", "Atlantis");
+        assert_eq!(
+            GameState::from_notation(&bad).err(),
+            Some(NotationError::BadField(
+                "influence",
+                "Turkey:0/2,Italy:4/0,Atlantis:0/4,USSR:0/6".to_string()
+            ))
+        );
+        // Without the optional cards they are not dealt into the draw pile
+        let basic = text.replace("+/2/USSR/+", "-/0/US/-");
+        let state = GameState::from_notation(&basic).unwrap();
+        assert!(!state.rules().optional_cards);
+        assert_eq!(state.rules().china_holder, Side::US);
+        assert!(state.deck.draw_pile().iter().all(|c| !c.is_optional()));
+        // Names are not case sensitive
+        let lower = text.replace("Poland", "poland").replace("Duck", "duck");
+        let parsed = GameState::from_notation(&lower).unwrap();
+        let expected = GameState::from_notation(text).unwrap().to_notation();
+        assert_eq!(parsed.to_notation(), expected);
+    }
+
+    #[test]
+    fn test_in_progress() {
+        let mut state = GameState::four_four_two();
+        state.turn = 2;
+        state.ar = 3;
+        state.add_pending(Decision::begin_ar(Side::USSR));
+        assert!(state.to_notation().is_ok());
+        state.china = true;
+        assert_eq!(state.to_notation(), Err(NotationError::InProgress("flags")));
+        state.china = false;
+        state.add_pending(Decision::conduct_ops(Side::USSR, 3));
+        assert_eq!(
+            state.to_notation(),
+            Err(NotationError::InProgress("pending"))
+        );
+        state.set_event(Card::Comecon);
+        let err = state.to_notation();
+        assert_eq!(err, Err(NotationError::InProgress("current_event")));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::card::{Card, Deck};
 use crate::country::Side;
+use crate::record::{RecordError, RecordErrorKind};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
 
@@ -74,6 +75,18 @@ pub struct DebugRand {
     pub discards: Vec<Option<Card>>,
     pub us_draw: Vec<Card>,
     pub ussr_draw: Vec<Card>,
+    /// The draw order after each reshuffle, first drawn first. Cards left out
+    /// stay beneath the listed ones in their current order.
+    pub reshuffles: Vec<Vec<Card>>,
+    /// Where a record names each card of `us_draw`, `ussr_draw` and
+    /// `reshuffles`, as the error to report if the card is not in the draw
+    /// pile. Empty unless parsed from a record.
+    pub us_draw_at: Vec<RecordError>,
+    pub ussr_draw_at: Vec<RecordError>,
+    pub reshuffles_at: Vec<Vec<RecordError>>,
+    /// The first card that was not in the draw pile when it had to be.
+    /// Replays stop with this error instead of panicking.
+    pub error: Option<RecordError>,
 }
 
 impl DebugRand {
@@ -93,6 +106,22 @@ impl DebugRand {
             discards,
             us_draw,
             ussr_draw,
+            reshuffles: Vec::new(),
+            us_draw_at: Vec::new(),
+            ussr_draw_at: Vec::new(),
+            reshuffles_at: Vec::new(),
+            error: None,
+        }
+    }
+    /// Notes that the card is not in the draw pile, keeping the first error.
+    fn missing(&mut self, at: Option<RecordError>, card: Card) {
+        if self.error.is_none() {
+            self.error = Some(at.unwrap_or_else(|| RecordError {
+                line: 0,
+                column: 0,
+                token: format!("{:?}", card),
+                reason: RecordErrorKind::NotInDrawPile,
+            }));
         }
     }
 }
@@ -116,23 +145,46 @@ impl TwilightRand for DebugRand {
         card
     }
     fn reshuffle(&mut self, deck: &mut Deck) {
-        todo!()
+        deck.reset_draw_pile();
+        if let Some(order) = self.reshuffles.pop() {
+            let at = self.reshuffles_at.pop().unwrap_or_default();
+            let pile = deck.draw_pile_mut();
+            // Cards are drawn from the end, so the first listed goes on top
+            for (i, c) in order.into_iter().enumerate().rev() {
+                match pile.iter().position(|&x| x == c) {
+                    Some(index) => {
+                        let c = pile.remove(index);
+                        pile.push(c);
+                    }
+                    None => self.missing(at.get(i).cloned(), c),
+                }
+            }
+        }
     }
     fn draw_card(&mut self, deck: &mut Deck, side: Side) -> Card {
-        let card = match side {
-            Side::US => self.us_draw.pop(),
-            Side::USSR => self.ussr_draw.pop(),
-            _ => None,
+        let (card, at) = match side {
+            Side::US => (self.us_draw.pop(), self.us_draw_at.pop()),
+            Side::USSR => (self.ussr_draw.pop(), self.ussr_draw_at.pop()),
+            _ => (None, None),
         };
         if let Some(card) = card {
             // dbg!(card);
             if card == Card::The_China_Card {
                 panic!("Should not draw the China Card");
             }
-            // dbg!(deck.draw_pile());
-            let index = deck.draw_pile().iter().position(|&c| c == card).unwrap();
-            deck.draw_pile_mut().swap_remove(index);
-            card
+            if deck.draw_pile().is_empty() {
+                self.reshuffle(deck);
+            }
+            match deck.draw_pile().iter().position(|&c| c == card) {
+                Some(index) => {
+                    deck.draw_pile_mut().swap_remove(index);
+                    card
+                }
+                None => {
+                    self.missing(at, card);
+                    Card::Dummy
+                }
+            }
         } else {
             // We've drawn all the known cards we care about, so just draw
             // a dummy card for now
@@ -140,3 +192,152 @@ impl TwilightRand for DebugRand {
         }
     }
 }
+
+/// A random outcome logged by `RecordingRand`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RandEvent {
+    Roll(Side, i8),
+    CardFromHand(Side, Option<Card>),
+    Draw(Side, Card),
+    /// The draw pile right after a reshuffle, bottom first.
+    Reshuffle(Vec<Card>),
+}
+
+/// Wraps another `TwilightRand`, logging every outcome it produces so that
+/// the game can be replayed with a `DebugRand`.
+#[derive(Clone)]
+pub struct RecordingRand<R: TwilightRand> {
+    inner: R,
+    log: Vec<RandEvent>,
+}
+
+impl<R: TwilightRand> RecordingRand<R> {
+    pub fn new(inner: R) -> Self {
+        RecordingRand {
+            inner,
+            log: Vec::new(),
+        }
+    }
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+    /// Returns the events logged so far, in the order they happened.
+    pub fn log(&self) -> &[RandEvent] {
+        &self.log
+    }
+    /// Removes and returns the events logged so far.
+    pub fn take_log(&mut self) -> Vec<RandEvent> {
+        std::mem::take(&mut self.log)
+    }
+    /// Returns a `DebugRand` that reproduces the logged rolls, draws,
+    /// reshuffles and cards taken from hand.
+    pub fn to_debug_rand(&self) -> DebugRand {
+        let mut rng = DebugRand::new_empty();
+        for event in self.log.iter() {
+            match *event {
+                RandEvent::Roll(Side::US, x) => rng.us_rolls.push(x),
+                RandEvent::Roll(_, x) => rng.ussr_rolls.push(x),
+                RandEvent::CardFromHand(_, c) => rng.discards.push(c),
+                RandEvent::Draw(Side::US, c) => rng.us_draw.push(c),
+                RandEvent::Draw(_, c) => rng.ussr_draw.push(c),
+                RandEvent::Reshuffle(ref pile) => {
+                    rng.reshuffles.push(pile.iter().rev().copied().collect())
+                }
+            }
+        }
+        // DebugRand pops from the back
+        rng.us_rolls.reverse();
+        rng.ussr_rolls.reverse();
+        rng.discards.reverse();
+        rng.us_draw.reverse();
+        rng.ussr_draw.reverse();
+        rng.reshuffles.reverse();
+        rng
+    }
+}
+
+impl<R: TwilightRand> TwilightRand for RecordingRand<R> {
+    fn roll(&mut self, side: Side) -> i8 {
+        let x = self.inner.roll(side);
+        self.log.push(RandEvent::Roll(side, x));
+        x
+    }
+    fn card_from_hand(&mut self, deck: &Deck, side: Side) -> Option<Card> {
+        let c = self.inner.card_from_hand(deck, side);
+        self.log.push(RandEvent::CardFromHand(side, c));
+        c
+    }
+    fn reshuffle(&mut self, deck: &mut Deck) {
+        self.inner.reshuffle(deck);
+        self.log
+            .push(RandEvent::Reshuffle(deck.draw_pile().clone()));
+    }
+    fn draw_card(&mut self, deck: &mut Deck, side: Side) -> Card {
+        // Reshuffle here so that it is logged, rather than inside the inner RNG
+        if deck.draw_pile().is_empty() {
+            self.reshuffle(deck);
+        }
+        let c = self.inner.draw_card(deck, side);
+        self.log.push(RandEvent::Draw(side, c));
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, RuleSet, Start};
+    use crate::state::GameState;
+    use crate::tensor::OutputIndex;
+
+    #[test]
+    fn test_replay_log() {
+        let rng = RecordingRand::new(InternalRand::new_seeded(21));
+        let mut game = Game::new(GameState::new(), rng, RuleSet::default());
+        game.setup(Start::Beginning);
+        let mut picker = SmallRng::seed_from_u64(4);
+        let mut choices = Vec::new();
+        while game.state.turn < 2 {
+            let legal = game.legal();
+            let next = legal
+                .get(picker.gen_range(0, legal.len().max(1)))
+                .copied()
+                .unwrap_or(OutputIndex::pass());
+            choices.push(next);
+            if game.consume_action(next.decode()).is_err() {
+                break;
+            }
+        }
+        let log = game.rng.log();
+        assert!(log.iter().any(|e| matches!(e, RandEvent::Roll(..))));
+        let draws = log.iter().filter(|e| matches!(e, RandEvent::Draw(..)));
+        assert!(draws.count() >= 16);
+
+        let mut replay = Game::new(
+            GameState::new(),
+            game.rng.to_debug_rand(),
+            RuleSet::default(),
+        );
+        replay.setup(Start::Beginning);
+        for next in choices {
+            if replay.consume_action(next.decode()).is_err() {
+                break;
+            }
+        }
+        let (a, b) = (&game.state, &replay.state);
+        assert_eq!(
+            (a.turn, a.ar, a.vp, a.defcon()),
+            (b.turn, b.ar, b.vp, b.defcon())
+        );
+        assert_eq!(a.deck.us_hand(), b.deck.us_hand());
+        assert_eq!(a.deck.ussr_hand(), b.deck.ussr_hand());
+        assert_eq!(a.deck.discard_pile(), b.deck.discard_pile());
+        for (x, y) in a.countries.iter().zip(b.countries.iter()) {
+            assert_eq!((x.us, x.ussr), (y.us, y.ussr));
+        }
+        assert!(replay.rng.us_rolls.is_empty() && replay.rng.ussr_rolls.is_empty());
+    }
+}
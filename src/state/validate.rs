@@ -0,0 +1,159 @@
+use super::GameState;
+use crate::action::Action;
+use crate::card::Card;
+use crate::country::{CName, Side};
+
+/// An invariant of the engine that a `GameState` breaks.
+#[derive(Debug, PartialEq)]
+pub enum InvariantError {
+    /// The card is in more than one pile, or twice in one.
+    DuplicateCard(Card),
+    /// The card is in play but in none of the piles.
+    MissingCard(Card),
+    /// The card has not entered the deck yet, or is the China Card.
+    UnexpectedCard(Card),
+    NegativeInfluence(CName),
+    Defcon(i8),
+    Vp(i8),
+    Ar(i8),
+    /// The pending decision has the wrong agent: no player for a decision a
+    /// player must make or for the next one, or a player for bookkeeping.
+    PendingAgent(Action, Side),
+}
+
+impl GameState {
+    /// Checks the invariants the engine relies on between actions:
+    ///
+    /// - every card in play is in exactly one of the hands, the draw pile,
+    ///   the discard piles or the removed pile, with the China Card tracked
+    ///   separately by the deck
+    /// - influence is never negative
+    /// - DEFCON is within 1..=5 and VP within ±20
+    /// - the action round does not exceed the most either side may take
+    /// - every pending decision is for the US or the USSR, except for the
+    ///   bookkeeping of ending the action round or clearing the event, which
+    ///   is never the next decision
+    ///
+    /// Masked cards from a `PlayerView` only have to be unique, since the
+    /// cards they hide are unknown. A finished game may go past the VP
+    /// limits, so it should only be checked while the game is going on.
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        self.validate_cards()?;
+        for (i, c) in self.valid_countries().iter().enumerate() {
+            if c.us < 0 || c.ussr < 0 {
+                return Err(InvariantError::NegativeInfluence(CName::from_index(i)));
+            }
+        }
+        if self.defcon < 1 || self.defcon > 5 {
+            return Err(InvariantError::Defcon(self.defcon));
+        }
+        if self.vp < -20 || self.vp > 20 {
+            return Err(InvariantError::Vp(self.vp));
+        }
+        let max_ar = self.max_ar(Side::US).max(self.max_ar(Side::USSR));
+        if self.ar < 0 || self.ar > max_ar {
+            return Err(InvariantError::Ar(self.ar));
+        }
+        let pending = self.pending();
+        for (i, d) in pending.iter().enumerate() {
+            let bookkeeping = d.action == Action::EndAr || d.action == Action::ClearEvent;
+            let next = i + 1 == pending.len();
+            let agent_ok = match d.agent {
+                Side::US | Side::USSR => !bookkeeping,
+                Side::Neutral => bookkeeping && !next,
+            };
+            if !agent_ok {
+                return Err(InvariantError::PendingAgent(d.action, d.agent));
+            }
+        }
+        Ok(())
+    }
+    fn validate_cards(&self) -> Result<(), InvariantError> {
+        let deck = &self.deck;
+        let mut count = vec![0; Card::total()];
+        let piles = deck
+            .us_hand()
+            .iter()
+            .chain(deck.ussr_hand().iter())
+            .chain(deck.draw_pile().iter())
+            .chain(deck.discard_pile().iter())
+            .chain(deck.pending_discard().iter())
+            .chain(deck.removed().iter());
+        for &c in piles {
+            count[c as usize] += 1;
+        }
+        let masked = count[Card::Dummy as usize] > 0;
+        count[Card::Dummy as usize] = 0;
+        if count[Card::The_China_Card as usize] > 0 {
+            return Err(InvariantError::UnexpectedCard(Card::The_China_Card));
+        }
+        for c in self.cards_in_play() {
+            match count[c as usize] {
+                0 if !masked => return Err(InvariantError::MissingCard(c)),
+                0 | 1 => {}
+                _ => return Err(InvariantError::DuplicateCard(c)),
+            }
+            count[c as usize] = 0;
+        }
+        match count.iter().position(|&x| x > 0) {
+            Some(i) => Err(InvariantError::UnexpectedCard(Card::from_index(i))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Decision;
+
+    #[test]
+    fn test_validate() {
+        let mut state = GameState::four_four_two();
+        assert_eq!(state.validate(), Ok(()));
+        let card = state.deck.draw_pile()[0];
+        state.deck.us_hand_mut().push(card);
+        assert_eq!(state.validate(), Err(InvariantError::DuplicateCard(card)));
+        state.deck.draw_pile_mut().remove(0);
+        assert_eq!(state.validate(), Ok(()));
+        state.deck.removed_mut().push(Card::Glasnost);
+        assert_eq!(
+            state.validate(),
+            Err(InvariantError::UnexpectedCard(Card::Glasnost))
+        );
+        state.deck.removed_mut().clear();
+        state.deck.draw_pile_mut().pop();
+        assert!(matches!(
+            state.validate(),
+            Err(InvariantError::MissingCard(_))
+        ));
+
+        let mut state = GameState::new();
+        state.countries[CName::Italy as usize].us = -1;
+        assert_eq!(
+            state.validate(),
+            Err(InvariantError::NegativeInfluence(CName::Italy))
+        );
+        let mut state = GameState::new();
+        state.vp = -21;
+        assert_eq!(state.validate(), Err(InvariantError::Vp(-21)));
+        state.vp = 0;
+        state.ar = 7;
+        assert_eq!(state.validate(), Err(InvariantError::Ar(7)));
+
+        // Every pending decision is checked, not just the next one
+        let mut state = GameState::four_four_two();
+        state.add_pending(Decision::begin_ar(Side::USSR));
+        assert_eq!(state.validate(), Ok(()));
+        let mut place = Decision::new(Side::US, Action::Place, &[]);
+        place.agent = Side::Neutral;
+        state.clear_pending();
+        state.set_pending(vec![place, Decision::begin_ar(Side::USSR)]);
+        let err = InvariantError::PendingAgent(Action::Place, Side::Neutral);
+        assert_eq!(state.validate(), Err(err));
+        state.clear_pending();
+        state.add_pending(Decision::new(Side::Neutral, Action::EndAr, &[]));
+        let err = InvariantError::PendingAgent(Action::EndAr, Side::Neutral);
+        assert_eq!(state.validate(), Err(err));
+    }
+}
@@ -0,0 +1,310 @@
+use super::GameState;
+use crate::action::{Action, Decision};
+use crate::card::{Card, Effect};
+use crate::country::Side;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// The portion of a `GameState` that one side is entitled to see. The
+/// opponent's hand is masked with `Card::Dummy` placeholders except for cards
+/// revealed by an event, and the draw pile is masked entirely.
+#[derive(Clone)]
+pub struct PlayerView {
+    side: Side,
+    state: GameState,
+    known_opp: Vec<Card>,
+    hidden_opp: usize,
+}
+
+impl PlayerView {
+    /// Returns the side this view belongs to.
+    pub fn side(&self) -> Side {
+        self.side
+    }
+    /// Returns the masked game state.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+    /// Returns the cards in the opponent's hand that are known to this side.
+    pub fn known_opponent_cards(&self) -> &[Card] {
+        &self.known_opp
+    }
+    /// Returns how many cards in the opponent's hand are unknown.
+    pub fn hidden_opponent_cards(&self) -> usize {
+        self.hidden_opp
+    }
+    /// Returns true if the opponent's entire hand is known.
+    pub fn opponent_hand_revealed(&self) -> bool {
+        self.hidden_opp == 0
+    }
+    /// Samples a full game state consistent with everything this side can see.
+    /// The hidden opponent cards and the draw pile are dealt from the cards in
+    /// play that are not in this side's hand, the discard piles, the removed
+    /// pile or among the known opponent cards. Known opponent cards stay in
+    /// the opponent's hand, and if only the US scoring cards are revealed, no
+    /// scoring card is dealt to the rest of the US hand.
+    ///
+    /// If the pool is too small, e.g. in positions set up by hand, the
+    /// remaining slots are filled with `Card::Dummy`.
+    pub fn determinize<R: Rng>(&self, rng: &mut R) -> GameState {
+        let opp = self.side.opposite();
+        let mut state = self.state.clone();
+        let no_scoring =
+            opp == Side::US && state.has_effect(Side::USSR, Effect::US_Scoring_Revealed);
+        let mut pool = state.cards_in_play();
+        let deck = &state.deck;
+        let seen = deck
+            .hand(self.side)
+            .iter()
+            .chain(deck.discard_pile().iter())
+            .chain(deck.pending_discard().iter())
+            .chain(deck.removed().iter())
+            .chain(self.known_opp.iter());
+        for c in seen {
+            if let Some(i) = pool.iter().position(|x| x == c) {
+                pool.swap_remove(i);
+            }
+        }
+        pool.shuffle(rng);
+        let (mut hand_pool, mut rest): (Vec<_>, Vec<_>) = if no_scoring {
+            pool.into_iter().partition(|c| !c.is_scoring())
+        } else {
+            (pool, Vec::new())
+        };
+        let hand = state.deck.hand_mut(opp);
+        hand.truncate(self.known_opp.len());
+        for _ in 0..self.hidden_opp {
+            hand.push(hand_pool.pop().unwrap_or(Card::Dummy));
+        }
+        rest.append(&mut hand_pool);
+        rest.shuffle(rng);
+        for c in state.deck.draw_pile_mut().iter_mut() {
+            *c = rest.pop().unwrap_or(Card::Dummy);
+        }
+        // Headline choices were masked along with the hand, so rebuild them.
+        // A hidden headline already chosen is chosen again from the new hand.
+        if state.ar == 0 && state.current_event().is_none() {
+            let headline = Decision::headline(opp, &state);
+            for d in state.pending.iter_mut() {
+                let hidden_event = d.action == Action::Event
+                    && d.allowed.try_slice().is_some_and(|x| x.is_empty());
+                if d.agent == opp && (d.action == Action::ChooseCard || hidden_event) {
+                    *d = headline.clone();
+                }
+            }
+        }
+        state
+    }
+}
+
+impl GameState {
+    /// Returns the view of the game available to the given side.
+    pub fn observe(&self, side: Side) -> PlayerView {
+        let opp = side.opposite();
+        let opp_hand = self.deck.hand(opp);
+        let known_opp: Vec<Card> = if self.hand_revealed(opp) {
+            opp_hand.clone()
+        } else if opp == Side::US && self.has_effect(Side::USSR, Effect::US_Scoring_Revealed) {
+            opp_hand
+                .iter()
+                .copied()
+                .filter(|c| c.is_scoring())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let hidden_opp = opp_hand.len() - known_opp.len();
+        // Cards that must not leak through the pending decisions of the opponent
+        let mut hidden: Vec<Card> = self.deck.draw_pile().clone();
+        let mut known = known_opp.clone();
+        for &c in opp_hand.iter() {
+            if let Some(i) = known.iter().position(|&k| k == c) {
+                known.swap_remove(i);
+            } else {
+                hidden.push(c);
+            }
+        }
+
+        let mut state = self.clone();
+        state.events = None;
+        let masked = state.deck.hand_mut(opp);
+        masked.clear();
+        masked.extend(known_opp.iter().copied());
+        masked.extend(std::iter::repeat_n(Card::Dummy, hidden_opp));
+        for c in state.deck.draw_pile_mut().iter_mut() {
+            *c = Card::Dummy;
+        }
+        for d in state.pending.iter_mut() {
+            if d.agent == opp && is_card_action(d.action) {
+                d.allowed = d
+                    .allowed
+                    .force_slice(self)
+                    .iter()
+                    .copied()
+                    .filter(|&x| !hidden.iter().any(|&c| c as usize == x))
+                    .collect::<Vec<_>>()
+                    .into();
+            }
+        }
+        PlayerView {
+            side,
+            state,
+            known_opp,
+            hidden_opp,
+        }
+    }
+    /// Returns true if the given side's entire hand is visible to its opponent.
+    fn hand_revealed(&self, side: Side) -> bool {
+        match side {
+            Side::US => {
+                self.has_effect(Side::USSR, Effect::US_Hand_Revealed)
+                    || self.has_effect(Side::USSR, Effect::AldrichAmes)
+            }
+            Side::USSR => self.has_effect(Side::US, Effect::USSR_Hand_Revealed),
+            Side::Neutral => unimplemented!(),
+        }
+    }
+}
+
+fn is_card_action(action: Action) -> bool {
+    use Action::*;
+    matches!(
+        action,
+        Ops | OpsEvent | Event | EventOps | Space | Discard | ChooseCard | RecoverCard
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::TensorOutput;
+
+    fn deal(state: &mut GameState, side: Side, cards: &[Card]) {
+        for &card in cards {
+            let draw = state.deck.draw_pile_mut();
+            let index = draw.iter().position(|&c| c == card).unwrap();
+            draw.swap_remove(index);
+            state.deck.hand_mut(side).push(card);
+        }
+    }
+
+    fn example_state() -> GameState {
+        let mut state = GameState::four_four_two();
+        let us = [Card::NATO, Card::Asia_Scoring, Card::Containment];
+        deal(&mut state, Side::US, &us);
+        deal(
+            &mut state,
+            Side::USSR,
+            &[Card::Comecon, Card::Europe_Scoring],
+        );
+        state
+    }
+
+    fn sorted(cards: &[Card]) -> Vec<usize> {
+        let mut vec: Vec<_> = cards.iter().map(|&c| c as usize).collect();
+        vec.sort();
+        vec
+    }
+
+    #[test]
+    fn test_hidden_hand() {
+        let state = example_state();
+        let view = state.observe(Side::US);
+        let masked = view.state();
+        assert_eq!(masked.deck.us_hand(), state.deck.us_hand());
+        assert_eq!(masked.deck.ussr_hand(), &vec![Card::Dummy; 2]);
+        assert_eq!(masked.deck.draw_pile().len(), state.deck.draw_pile().len());
+        assert!(masked.deck.draw_pile().iter().all(|&c| c == Card::Dummy));
+        assert_eq!(view.hidden_opponent_cards(), 2);
+        assert!(view.known_opponent_cards().is_empty());
+    }
+
+    #[test]
+    fn test_revealed_hand() {
+        let mut state = example_state();
+        state.add_effect(Side::US, Effect::USSR_Hand_Revealed);
+        let view = state.observe(Side::US);
+        assert!(view.opponent_hand_revealed());
+        assert_eq!(view.state().deck.ussr_hand(), state.deck.ussr_hand());
+        // The reveal is one way
+        let view = state.observe(Side::USSR);
+        assert_eq!(view.hidden_opponent_cards(), 3);
+
+        let mut state = example_state();
+        state.add_effect(Side::USSR, Effect::US_Scoring_Revealed);
+        let view = state.observe(Side::USSR);
+        assert_eq!(view.known_opponent_cards(), &[Card::Asia_Scoring]);
+        assert_eq!(
+            view.state().deck.us_hand(),
+            &vec![Card::Asia_Scoring, Card::Dummy, Card::Dummy]
+        );
+    }
+
+    #[test]
+    fn test_hidden_headline() {
+        let mut state = example_state();
+        let us_hl = Decision::headline(Side::US, &state);
+        let ussr_hl = Decision::headline(Side::USSR, &state);
+        state.set_pending(vec![ussr_hl, us_hl]);
+        let mut view = state.observe(Side::US);
+        let pending = &mut view.state.pending;
+        assert_eq!(pending[1].encode(&state).len(), 3);
+        // The USSR headline choices would reveal their hand
+        assert!(pending[0].encode(&state).is_empty());
+    }
+
+    #[test]
+    fn test_determinize() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut state = example_state();
+        state.discard_card(Side::US, Card::NATO);
+        state.flush_pending();
+        let view = state.observe(Side::US);
+        let mut hidden = state.deck.ussr_hand().clone();
+        hidden.extend(state.deck.draw_pile().iter().copied());
+        for _ in 0..10 {
+            let sample = view.determinize(&mut rng);
+            assert_eq!(sample.deck.us_hand(), state.deck.us_hand());
+            assert_eq!(sample.deck.ussr_hand().len(), 2);
+            let mut dealt = sample.deck.ussr_hand().clone();
+            dealt.extend(sample.deck.draw_pile().iter().copied());
+            assert_eq!(sorted(&dealt), sorted(&hidden));
+        }
+    }
+
+    #[test]
+    fn test_determinize_chosen_headline() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+        let mut rng = SmallRng::seed_from_u64(7);
+        let state = example_state();
+        let us_hl = Decision::headline(Side::US, &state);
+        let chosen = Decision::new(Side::USSR, Action::Event, vec![Card::Comecon as usize]);
+        let mut state = state;
+        state.set_pending(vec![chosen, us_hl]);
+        let sample = state.observe(Side::US).determinize(&mut rng);
+        let ussr = &sample.pending()[0];
+        assert_eq!(ussr.action, Action::ChooseCard);
+        assert_eq!(ussr.allowed.try_slice().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_determinize_scoring_revealed() {
+        use rand::rngs::SmallRng;
+        use rand::SeedableRng;
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut state = example_state();
+        state.add_effect(Side::USSR, Effect::US_Scoring_Revealed);
+        let view = state.observe(Side::USSR);
+        for _ in 0..10 {
+            let sample = view.determinize(&mut rng);
+            let hand = sample.deck.us_hand();
+            assert_eq!(hand[0], Card::Asia_Scoring);
+            assert_eq!(hand.len(), 3);
+            assert!(hand[1..].iter().all(|c| !c.is_scoring()));
+        }
+    }
+}
@@ -3,6 +3,14 @@ use crate::card::{Card, Effect};
 use crate::country::{CName, Side};
 use crate::state::GameState;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod observation;
+pub use observation::*;
+pub mod space;
+pub use space::*;
+
 lazy_static! {
     pub static ref SPECIAL_TOTAL: usize = {
         let last = CARD_OFFSET.last_value();
@@ -29,6 +37,7 @@ lazy_static! {
 }
 
 #[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DecodedChoice {
     pub action: Action,
     pub choice: Option<usize>,
@@ -121,12 +130,18 @@ impl Decision {
     }
 }
 
-pub(crate) trait TensorOutput {
-    fn encode(&mut self, state: &GameState) -> OutputVec;
+pub trait TensorOutput {
+    fn encode(&mut self, state: &GameState) -> OutputVec {
+        let mut legal = Vec::new();
+        self.encode_into(state, &mut legal);
+        legal
+    }
+    /// Appends the encoded outputs to `legal`, reusing its allocation.
+    fn encode_into(&mut self, state: &GameState, legal: &mut OutputVec);
 }
 
 impl TensorOutput for Decision {
-    fn encode(&mut self, state: &GameState) -> OutputVec {
+    fn encode_into(&mut self, state: &GameState, legal: &mut OutputVec) {
         let begin = self.action.offset();
         let out = match self.action {
             Action::BeginAr => {
@@ -234,7 +249,9 @@ impl TensorOutput for Decision {
                         {
                             vec.push(Action::Event.offset() + index);
                         }
-                        vec.push(Action::Ops.offset() + index);
+                        if !card.is_scoring() {
+                            vec.push(Action::Ops.offset() + index);
+                        }
                     }
                     Side::USSR => {
                         if card.can_event(state, Side::US) && (state.ar != 0 || card.can_headline())
@@ -250,17 +267,13 @@ impl TensorOutput for Decision {
                 encode_offsets(vec)
             }
             _ => {
-                let v = self
-                    .allowed
-                    .force_slice(state)
-                    .iter()
-                    .copied()
-                    .map(|x| x + begin)
-                    .collect();
-                encode_offsets(v)
+                // The common case, written straight into the buffer
+                let allowed = self.allowed.force_slice(state);
+                legal.extend(allowed.iter().map(|&x| OutputIndex::new(x + begin)));
+                return;
             }
         };
-        out
+        legal.extend(out);
     }
 }
 
@@ -287,6 +300,11 @@ impl OutputIndex {
     pub fn inner(&self) -> usize {
         self.data
     }
+    /// Returns the number of distinct indices, i.e. the `legal_choices` of
+    /// every action summed.
+    pub fn total() -> usize {
+        Action::Pass.offset() + Action::Pass.legal_choices()
+    }
 }
 
 impl std::fmt::Debug for OutputIndex {
@@ -0,0 +1,215 @@
+use crate::card::{Card, NUM_EFFECTS};
+use crate::country::{Side, NUM_COUNTRIES};
+use crate::state::GameState;
+
+/// Bumped whenever the observation layout changes, so that models trained on
+/// an older layout can refuse to load.
+pub const OBSERVATION_VERSION: u32 = 1;
+
+/// Number of countries encoded, i.e. every country but the superpowers.
+const NUM_ENCODED_COUNTRIES: usize = NUM_COUNTRIES - 2;
+/// Per country: US influence, USSR influence, stability, US control, USSR
+/// control.
+const COUNTRY_FEATURES: usize = 5;
+
+/// A named, contiguous range of the observation vector.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub name: &'static str,
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl Segment {
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.len
+    }
+}
+
+/// Flattens a `GameState` into a fixed-size feature vector from the point of
+/// view of one side. All values are raw counts or 0/1 flags; normalization is
+/// left to the consumer.
+///
+/// The layout, in order:
+///
+/// | Segment        | Length             | Contents                                          |
+/// |----------------|--------------------|---------------------------------------------------|
+/// | `countries`    | 84 * 5             | US inf, USSR inf, stability, US ctrl, USSR ctrl   |
+/// | `vp`           | 1                  | Victory points, positive for the US               |
+/// | `defcon`       | 1                  | DEFCON level                                      |
+/// | `turn`         | 1                  | Turn number                                       |
+/// | `ar`           | 1                  | Action round, 0 during the headline               |
+/// | `phasing`      | 2                  | One-hot phasing side (US, USSR)                   |
+/// | `acting`       | 2                  | One-hot side the observation is for (US, USSR)    |
+/// | `space`        | 2                  | Space race position (US, USSR)                    |
+/// | `mil_ops`      | 2                  | Military operations (US, USSR)                    |
+/// | `us_effects`   | `NUM_EFFECTS`      | Count of each active US effect                    |
+/// | `ussr_effects` | `NUM_EFFECTS`      | Count of each active USSR effect                  |
+/// | `china`        | 3                  | US holds, USSR holds, face up                     |
+/// | `hand`         | `Card::total()`    | Count of each card in the acting side's hand      |
+///
+/// Countries and cards are indexed by their `CName` and `Card` discriminants.
+pub struct ObservationEncoder {
+    segments: Vec<Segment>,
+    len: usize,
+}
+
+impl ObservationEncoder {
+    pub fn new() -> Self {
+        let sizes = [
+            ("countries", NUM_ENCODED_COUNTRIES * COUNTRY_FEATURES),
+            ("vp", 1),
+            ("defcon", 1),
+            ("turn", 1),
+            ("ar", 1),
+            ("phasing", 2),
+            ("acting", 2),
+            ("space", 2),
+            ("mil_ops", 2),
+            ("us_effects", NUM_EFFECTS),
+            ("ussr_effects", NUM_EFFECTS),
+            ("china", 3),
+            ("hand", Card::total()),
+        ];
+        let mut offset = 0;
+        let segments = sizes
+            .iter()
+            .map(|&(name, len)| {
+                let s = Segment { name, offset, len };
+                offset += len;
+                s
+            })
+            .collect();
+        ObservationEncoder {
+            segments,
+            len: offset,
+        }
+    }
+    pub fn version(&self) -> u32 {
+        OBSERVATION_VERSION
+    }
+    /// Returns the total length of an encoded observation.
+    pub fn size(&self) -> usize {
+        self.len
+    }
+    pub fn layout(&self) -> &[Segment] {
+        &self.segments
+    }
+    pub fn segment(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.name == name)
+    }
+    /// Encodes the state as seen by the given side into a new vector.
+    pub fn encode(&self, state: &GameState, side: Side) -> Vec<f32> {
+        let mut out = vec![0.0; self.len];
+        self.encode_into(state, side, &mut out);
+        out
+    }
+    /// Encodes the state as seen by the given side into an existing buffer,
+    /// which must be exactly `size()` long.
+    pub fn encode_into(&self, state: &GameState, side: Side, out: &mut [f32]) {
+        assert_eq!(out.len(), self.len);
+        assert_ne!(side, Side::Neutral);
+        for x in out.iter_mut() {
+            *x = 0.0;
+        }
+        let r = |i: usize| self.segments[i].range();
+        let countries = &mut out[r(0)];
+        for (i, c) in state.valid_countries().iter().enumerate() {
+            let x = &mut countries[i * COUNTRY_FEATURES..(i + 1) * COUNTRY_FEATURES];
+            x[0] = c.us as f32;
+            x[1] = c.ussr as f32;
+            x[2] = c.stability as f32;
+            match c.controller() {
+                Side::US => x[3] = 1.0,
+                Side::USSR => x[4] = 1.0,
+                Side::Neutral => {}
+            }
+        }
+        out[r(1)][0] = state.vp as f32;
+        out[r(2)][0] = state.defcon() as f32;
+        out[r(3)][0] = state.turn as f32;
+        out[r(4)][0] = state.ar as f32;
+        one_hot(&mut out[r(5)], state.side);
+        one_hot(&mut out[r(6)], side);
+        for s in [Side::US, Side::USSR].iter().map(|&s| s as usize) {
+            out[r(7)][s] = state.space[s] as f32;
+            out[r(8)][s] = state.mil_ops[s] as f32;
+        }
+        for &e in state.us_effects() {
+            out[r(9)][e as usize] += 1.0;
+        }
+        for &e in state.ussr_effects() {
+            out[r(10)][e as usize] += 1.0;
+        }
+        let china = &mut out[r(11)];
+        let holder = state.deck.china();
+        one_hot(&mut china[0..2], holder);
+        if state.deck.china_available(holder) {
+            china[2] = 1.0;
+        }
+        let hand = &mut out[r(12)];
+        for &c in state.deck.hand(side) {
+            hand[c as usize] += 1.0;
+        }
+    }
+}
+
+impl Default for ObservationEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn one_hot(out: &mut [f32], side: Side) {
+    match side {
+        Side::US => out[0] = 1.0,
+        Side::USSR => out[1] = 1.0,
+        Side::Neutral => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Effect;
+    use crate::country::CName;
+
+    #[test]
+    fn test_layout() {
+        let encoder = ObservationEncoder::new();
+        let last = encoder.layout().last().unwrap();
+        assert_eq!(last.offset + last.len, encoder.size());
+        for w in encoder.layout().windows(2) {
+            assert_eq!(w[0].offset + w[0].len, w[1].offset);
+        }
+        let state = GameState::new();
+        assert_eq!(encoder.encode(&state, Side::US).len(), encoder.size());
+    }
+
+    #[test]
+    fn test_encode() {
+        let encoder = ObservationEncoder::new();
+        let mut state = GameState::four_four_two();
+        state.vp = -3;
+        state.add_effect(Side::US, Effect::Containment);
+        state.deck.us_hand_mut().push(Card::NATO);
+        state.deck.ussr_hand_mut().push(Card::Comecon);
+        let obs = encoder.encode(&state, Side::US);
+        let get = |name: &str| &obs[encoder.segment(name).unwrap().range()];
+
+        let italy = CName::Italy as usize * COUNTRY_FEATURES;
+        assert_eq!(
+            &get("countries")[italy..italy + 5],
+            &[4.0, 0.0, 2.0, 1.0, 0.0]
+        );
+        assert_eq!(get("vp"), &[-3.0]);
+        assert_eq!(get("defcon"), &[5.0]);
+        assert_eq!(get("us_effects")[Effect::Containment as usize], 1.0);
+        assert_eq!(get("china"), &[0.0, 1.0, 1.0]);
+        assert_eq!(get("acting"), &[1.0, 0.0]);
+        // Only the acting side's hand is encoded
+        let hand = get("hand");
+        assert_eq!(hand[Card::NATO as usize], 1.0);
+        assert_eq!(hand[Card::Comecon as usize], 0.0);
+    }
+}
@@ -0,0 +1,210 @@
+use super::{DecodedChoice, OutputIndex};
+use crate::action::{Action, NUM_ACTIONS};
+use crate::card::Card;
+use crate::country::{CName, Region};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the meaning of an index changes without changing its name,
+/// which the layout fingerprint cannot see.
+pub const ACTION_SPACE_VERSION: u32 = 1;
+
+/// What the choices of an action range over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChoiceKind {
+    /// A single index with nothing to choose.
+    Unit,
+    Card,
+    Country,
+    Region,
+    Number,
+}
+
+/// The indices of one action, `start..start + len`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActionRange {
+    pub action: Action,
+    pub start: usize,
+    pub len: usize,
+    pub kind: ChoiceKind,
+}
+
+/// Identifies a layout of the action space, to be stored with anything that
+/// depends on it, such as model weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ActionSpaceId {
+    pub version: u32,
+    pub size: usize,
+    /// A hash of the name of every index.
+    pub fingerprint: u64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ActionSpaceError {
+    Incompatible {
+        expected: ActionSpaceId,
+        found: ActionSpaceId,
+    },
+}
+
+/// The flat action layout of this build, mapping every `OutputIndex` to an
+/// action and its choice.
+pub struct ActionSpace {
+    ranges: Vec<ActionRange>,
+}
+
+impl ActionSpace {
+    pub fn new() -> Self {
+        let ranges = (0..NUM_ACTIONS)
+            .map(|i| {
+                let action = Action::from_index(i);
+                ActionRange {
+                    action,
+                    start: action.offset(),
+                    len: action.legal_choices(),
+                    kind: choice_kind(action),
+                }
+            })
+            .collect();
+        ActionSpace { ranges }
+    }
+    pub fn size(&self) -> usize {
+        let last = self.ranges.last().unwrap();
+        last.start + last.len
+    }
+    pub fn ranges(&self) -> &[ActionRange] {
+        &self.ranges
+    }
+    pub fn range(&self, action: Action) -> &ActionRange {
+        &self.ranges[action as usize]
+    }
+    pub fn id(&self) -> ActionSpaceId {
+        // FNV-1a, which unlike the std hashers is fixed across builds
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for i in 0..self.size() {
+            for b in self.name(i).unwrap().bytes().chain(Some(b'\n')) {
+                hash ^= b as u64;
+                hash = hash.wrapping_mul(0x100_0000_01b3);
+            }
+        }
+        ActionSpaceId {
+            version: ACTION_SPACE_VERSION,
+            size: self.size(),
+            fingerprint: hash,
+        }
+    }
+    /// Fails unless the layout `id` was made from is the same as this one.
+    pub fn check(&self, id: &ActionSpaceId) -> Result<(), ActionSpaceError> {
+        let expected = self.id();
+        if expected == *id {
+            Ok(())
+        } else {
+            Err(ActionSpaceError::Incompatible {
+                expected,
+                found: *id,
+            })
+        }
+    }
+    /// Returns the index of the choice, or None if the choice is out of the
+    /// action's range. A missing choice is only valid for unit actions.
+    pub fn encode(&self, decoded: &DecodedChoice) -> Option<OutputIndex> {
+        let range = self.range(decoded.action);
+        let choice = match decoded.choice {
+            Some(c) => c,
+            None if range.len == 1 => 0,
+            None => return None,
+        };
+        if choice < range.len {
+            Some(OutputIndex::new(range.start + choice))
+        } else {
+            None
+        }
+    }
+    pub fn decode(&self, index: usize) -> Option<DecodedChoice> {
+        if index < self.size() {
+            Some(OutputIndex::new(index).decode())
+        } else {
+            None
+        }
+    }
+    /// Names the index by its action and choice, e.g. "Coup Iran" or
+    /// "Event Duck_and_Cover".
+    pub fn name(&self, index: usize) -> Option<String> {
+        let decoded = self.decode(index)?;
+        let action = decoded.action;
+        let c = decoded.choice.unwrap_or(0);
+        let choice = match choice_kind(action) {
+            ChoiceKind::Unit => return Some(format!("{:?}", action)),
+            ChoiceKind::Card => format!("{:?}", Card::from_index(c)),
+            ChoiceKind::Country => {
+                let country = match action {
+                    Action::CubanMissile => [CName::Cuba, CName::WGermany, CName::Turkey][c],
+                    Action::DoubleInf => {
+                        let (low, _) = Region::SouthAmerica.low_high();
+                        CName::from_index(low + c)
+                    }
+                    _ => CName::from_index(c),
+                };
+                format!("{:?}", country)
+            }
+            ChoiceKind::Region => format!("{:?}", Region::major_regions()[c]),
+            ChoiceKind::Number => c.to_string(),
+        };
+        Some(format!("{:?} {}", action, choice))
+    }
+}
+
+impl Default for ActionSpace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn choice_kind(action: Action) -> ChoiceKind {
+    use Action::*;
+    match action {
+        Space | Discard | Event | EventOps | Ops | OpsEvent | RecoverCard | ChooseCard => {
+            ChoiceKind::Card
+        }
+        Influence | Coup | Realignment | Place | Remove | War | CubanMissile | DoubleInf => {
+            ChoiceKind::Country
+        }
+        BlockRegion => ChoiceKind::Region,
+        SpecialEvent | ChangeDefcon => ChoiceKind::Number,
+        BeginAr | EndAr | ClearEvent | ConductOps | Pass => ChoiceKind::Unit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_space() {
+        let space = ActionSpace::new();
+        assert_eq!(space.size(), OutputIndex::total());
+        for i in 0..space.size() {
+            let decoded = space.decode(i).unwrap();
+            assert_eq!(space.encode(&decoded), Some(OutputIndex::new(i)));
+        }
+        assert_eq!(space.decode(space.size()), None);
+        let coup = DecodedChoice::new(Action::Coup, Some(CName::Iran as usize));
+        let i = space.encode(&coup).unwrap().inner();
+        assert_eq!(space.name(i).unwrap(), "Coup Iran");
+        let pass = DecodedChoice::new(Action::Pass, None);
+        assert_eq!(space.encode(&pass), Some(OutputIndex::pass()));
+        assert_eq!(space.name(OutputIndex::pass().inner()).unwrap(), "Pass");
+        let cuba = DecodedChoice::new(Action::CubanMissile, Some(3));
+        assert_eq!(space.encode(&cuba), None);
+
+        let id = space.id();
+        assert_eq!(space.check(&id), Ok(()));
+        let old = ActionSpaceId {
+            fingerprint: id.fingerprint ^ 1,
+            ..id
+        };
+        assert!(space.check(&old).is_err());
+    }
+}
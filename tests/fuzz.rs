@@ -0,0 +1,32 @@
+use ts_engine::fuzz::random_walks;
+
+/// Sweeps random walks over many seeds. More seeds can be swept with:
+///
+/// ```text
+/// FUZZ_SEEDS=10000 cargo test --release --test fuzz -- --nocapture
+/// ```
+#[test]
+fn random_walk_sweep() {
+    let seeds = std::env::var("FUZZ_SEEDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(200);
+    let failures = random_walks(0..seeds, 5000);
+    for f in failures.iter() {
+        println!(
+            "Seed {} from {:?} panicked after {} moves: {}",
+            f.seed,
+            f.start,
+            f.actions.len(),
+            f.message
+        );
+        let moves: Vec<_> = f.actions.iter().map(|x| x.decode()).collect();
+        println!("{:?}", moves);
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} walks panicked",
+        failures.len(),
+        seeds
+    );
+}
@@ -50,16 +50,17 @@ fn check_controlled(state: &GameState, us: &[CName], ussr: &[CName]) {
 #[test]
 fn test_traps() {
     let text = load_file("tests/Traps1.record");
-    let record = parse_lines(&text);
+    let record = parse_lines(&text).unwrap();
     let mut replay: Replay = record.into();
     replay.game.four_four_two();
     // replay.game.setup();
-    replay.game.state.deck.add_mid_war();
+    replay.game.state.add_mid_war();
     replay.add_check(Box::new(|x: &Replay| assert!(x.history.len() == 9)));
     // replay.game.draw_hands();
     // dbg!(game.state.deck.us_hand());
     // dbg!(game.state.deck.ussr_hand());
-    assert!(replay.play(Start::HL(4)).is_none());
+    assert!(replay.play(Start::HL(4)).unwrap().is_none());
+    assert_eq!(replay.game.state.validate(), Ok(()));
     // dbg!(game.state.deck.removed());
     // dbg!(game.state.deck.discard_pile());
     // dbg!(game.state.deck.us_hand());
@@ -70,9 +71,10 @@ fn test_traps() {
 fn events1() {
     use ts_engine::country::{CName::*, Side};
     let s = load_file("tests/Events1.record");
-    let mut replay: Replay = ts_engine::record::parse_lines(&s).into();
-    let res = replay.play(Start::Beginning);
+    let mut replay: Replay = ts_engine::record::parse_lines(&s).unwrap().into();
+    let res = replay.play(Start::Beginning).unwrap();
     assert!(res.is_none());
+    assert_eq!(replay.game.state.validate(), Ok(()));
     let us = [
         (Canada, 3),
         (UK, 4),
@@ -117,7 +119,7 @@ fn test_events2() {
     const US1: [CName; 6] = [UK, Italy, Thailand, LaosCambodia, Australia, Iran];
     const USSR1: [CName; 6] = [Poland, EGermany, Egypt, Vietnam, SKorea, NKorea];
     let s = load_file("tests/Events2.record");
-    let mut replay: Replay = ts_engine::record::parse_lines(&s).into();
+    let mut replay: Replay = ts_engine::record::parse_lines(&s).unwrap().into();
     replay.add_check(Box::new(|replay| assert_eq!(replay.game.state.ar, 1)));
     replay.add_check(Box::new(|replay| {
         let state = &replay.game.state;
@@ -126,8 +128,9 @@ fn test_events2() {
         assert_eq!(state.vp, 2);
         check_controlled(state, &US1, &USSR1);
     }));
-    let res = replay.play(Start::Beginning);
+    let res = replay.play(Start::Beginning).unwrap();
     assert!(res.is_none());
+    assert_eq!(replay.game.state.validate(), Ok(()));
 }
 
 #[test]
@@ -135,9 +138,10 @@ fn parse_one_turn() {
     use ts_engine::card::Card;
     use ts_engine::country::{CName::*, Side};
     let s = load_file("tests/Brashers_Ziemovit2020.record");
-    let mut replay: Replay = ts_engine::record::parse_lines(&s).into();
+    let mut replay: Replay = ts_engine::record::parse_lines(&s).unwrap().into();
     assert_eq!(replay.game.rng.us_rolls, vec![6, 1]);
-    assert!(replay.play(Start::Beginning).is_none());
+    assert!(replay.play(Start::Beginning).unwrap().is_none());
+    assert_eq!(replay.game.state.validate(), Ok(()));
     let us = [
         (Canada, 2),
         (UK, 3),
@@ -209,3 +213,34 @@ fn parse_one_turn() {
         ]
     );
 }
+
+#[test]
+fn test_reshuffle() {
+    use ts_engine::card::Card;
+    let s = load_file("tests/Reshuffle1.record");
+    let mut replay: Replay = parse_lines(&s).unwrap().into();
+    assert!(replay.play(Start::Beginning).unwrap().is_none());
+    let deck = &replay.game.state.deck;
+    assert_eq!(replay.game.state.turn, 3);
+    assert!(deck.us_hand().contains(&Card::Nuclear_Test_Ban));
+    assert!(deck.ussr_hand().contains(&Card::NATO));
+    assert!(deck.discard_pile().is_empty());
+    assert_eq!(deck.draw_pile().len(), 7);
+    assert!(deck.draw_pile().contains(&Card::Arab_Israeli_War));
+
+    let error_at = |text: &str, prefix: &str| {
+        let mut replay: Replay = parse_lines(text).unwrap().into();
+        let e = replay.play(Start::Beginning).unwrap_err();
+        let line = text.lines().position(|l| l.starts_with(prefix)).unwrap();
+        assert_eq!(e.reason, RecordErrorKind::NotInDrawPile);
+        (e.line - line - 1, e.column, e.token)
+    };
+    // The Marshall Plan was removed, so it cannot be reshuffled into place
+    let bad = s.replace("Reshuffle NATO", "Reshuffle Marshall NATO");
+    let e = error_at(&bad, "Reshuffle");
+    assert_eq!(e, (0, 11, "Marshall".to_string()));
+    // Nasser is still in the USSR hand, which must not reshuffle early
+    let bad = s.replace("US Fidel Touch", "US Nasser Touch");
+    let e = error_at(&bad, "US Nasser Touch");
+    assert_eq!(e, (0, 4, "Nasser".to_string()));
+}